@@ -0,0 +1,399 @@
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::capabilities::classify;
+use crate::context::Context;
+use crate::jsonrpc::{self, CallError};
+use crate::transport::Transport;
+use crate::types::{CheckResult, Finding, ResultData, Severity, Support};
+
+/// Sensitive Ethereum methods. Methods that would change node state are
+/// called without their required arguments, so a node that has them fails
+/// parameter validation instead of executing anything.
+const ETHEREUM_METHODS: &[(&str, Severity, &str)] = &[
+    (
+        "admin_nodeInfo",
+        Severity::High,
+        "admin namespace is exposed (peer management, node control)",
+    ),
+    (
+        "personal_listAccounts",
+        Severity::Critical,
+        "personal namespace is exposed (account unlocking and signing)",
+    ),
+    (
+        "miner_setExtra",
+        Severity::High,
+        "miner namespace is exposed (mining and fee configuration)",
+    ),
+    (
+        "debug_setHead",
+        Severity::Critical,
+        "debug_setHead is exposed (chain can be rewound)",
+    ),
+    (
+        "txpool_status",
+        Severity::Low,
+        "txpool namespace is exposed (mempool contents readable)",
+    ),
+];
+
+/// Bitcoin Core methods that act on a loaded wallet.
+const WALLET_METHODS: &[&str] = &["getwalletinfo", "dumpprivkey"];
+
+/// Sensitive Bitcoin Core methods, probed the same way. `getwalletinfo`
+/// comes first: it tells whether a wallet is loaded at all.
+const BITCOIN_METHODS: &[(&str, Severity, &str)] = &[
+    (
+        "getwalletinfo",
+        Severity::Critical,
+        "a wallet is loaded and its RPCs are reachable",
+    ),
+    ("listwallets", Severity::Medium, "wallet RPCs are enabled"),
+    (
+        "dumpprivkey",
+        Severity::Critical,
+        "private key export is exposed",
+    ),
+    ("addnode", Severity::Medium, "peer management is exposed"),
+    ("setban", Severity::Medium, "ban management is exposed"),
+    ("getpeerinfo", Severity::Low, "peer addresses are readable"),
+];
+
+/// Tendermint routes only served with `rpc.unsafe = true`.
+const TENDERMINT_ROUTES: &[(&str, Severity, &str)] = &[
+    (
+        "dial_seeds",
+        Severity::High,
+        "unsafe route /dial_seeds is exposed (peer injection)",
+    ),
+    (
+        "dial_peers",
+        Severity::High,
+        "unsafe route /dial_peers is exposed (peer injection)",
+    ),
+    (
+        "unsafe_flush_mempool",
+        Severity::High,
+        "unsafe route /unsafe_flush_mempool is exposed",
+    ),
+    (
+        "unsafe_start_cpu_profiler",
+        Severity::Medium,
+        "profiling routes are exposed",
+    ),
+    (
+        "unsafe_write_heap_profile",
+        Severity::Medium,
+        "profiling routes are exposed",
+    ),
+];
+
+pub async fn check_ethereum_audit(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let mut audit = Audit::default();
+
+    let mut calls: Vec<(&str, serde_json::Value)> = ETHEREUM_METHODS
+        .iter()
+        .map(|(method, _, _)| (*method, json!([])))
+        .collect();
+    calls.push(("eth_accounts", json!([])));
+
+    let mut outcomes = transport.batch(calls).await;
+    let accounts = outcomes
+        .pop()
+        .expect("one result per call")
+        .map(|v| v.as_array().is_some_and(|a| !a.is_empty()));
+
+    for (&probe, outcome) in ETHEREUM_METHODS.iter().zip(outcomes) {
+        audit.record(probe, outcome);
+    }
+
+    audit.record_present(
+        (
+            "eth_accounts",
+            Severity::High,
+            "node manages accounts that may be used for signing",
+        ),
+        accounts,
+    );
+
+    audit.into_result("ethereum", rpc)
+}
+
+pub async fn check_bitcoin_audit(rpc: &str, ctx: &Context) -> CheckResult {
+    let mut audit = Audit::default();
+    let mut no_wallet = false;
+
+    for &probe in BITCOIN_METHODS {
+        let outcome = jsonrpc::call_bitcoin(&ctx.client, rpc, &ctx.auth, probe.0, json!([])).await;
+        audit.record_bitcoin(probe, outcome, &mut no_wallet);
+    }
+
+    audit.into_result("bitcoin", rpc)
+}
+
+pub async fn check_tendermint_audit(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let mut audit = Audit::default();
+
+    // The root page lists every route the node serves, which lets us detect
+    // unsafe routes without calling any of them.
+    match transport.get_text("").await {
+        Ok(body) if body.contains("Available endpoints") => {
+            for &probe in TENDERMINT_ROUTES {
+                let present = body.contains(&format!("/{}", probe.0));
+                audit.record_present(probe, Ok(present));
+            }
+        }
+        _ => {
+            // Proxies often hide the listing, and websockets never serve it.
+            // /dial_seeds without seeds only returns "no seeds provided" on
+            // nodes that serve it.
+            let probe = TENDERMINT_ROUTES[0];
+            match transport.route(probe.0, &[]).await {
+                // Over HTTP, routes that are not served answer 404.
+                Err(CallError::Http(StatusCode::NOT_FOUND)) => {
+                    audit.record_present(probe, Ok(false))
+                }
+                outcome => audit.record(probe, outcome),
+            }
+        }
+    }
+
+    audit.into_result("tendermint", rpc)
+}
+
+#[derive(Default)]
+struct Audit {
+    findings: Vec<Finding>,
+    probes: usize,
+    failures: usize,
+    error: Option<CallError>,
+}
+
+impl Audit {
+    /// Records a method probe: any answer other than a JSON-RPC "not found"
+    /// or "not allowed" (including rejected parameters) means the method is
+    /// served. Rate limiting and HTTP-level refusals say nothing about the
+    /// method, so they count as failed probes rather than absent methods.
+    fn record(
+        &mut self,
+        probe: (&str, Severity, &str),
+        outcome: Result<serde_json::Value, CallError>,
+    ) {
+        let present = match outcome {
+            Ok(_) => Ok(true),
+            Err(e) => match (classify(&e), &e) {
+                (Support::Supported, _) => Ok(true),
+                (Support::NotFound | Support::Forbidden, CallError::Rpc { status, .. })
+                    if *status != StatusCode::UNAUTHORIZED =>
+                {
+                    Ok(false)
+                }
+                _ => Err(e),
+            },
+        };
+
+        self.record_present(probe, present);
+    }
+
+    /// Records a Bitcoin probe. Bitcoin Core rejects missing arguments
+    /// before it looks for a wallet, so once `getwalletinfo` reports that no
+    /// wallet is loaded, the wallet methods count as absent whatever they
+    /// answer.
+    fn record_bitcoin(
+        &mut self,
+        probe: (&str, Severity, &str),
+        outcome: Result<serde_json::Value, CallError>,
+        no_wallet: &mut bool,
+    ) {
+        if matches!(&outcome, Err(e) if no_wallet_loaded(e)) {
+            *no_wallet = true;
+        }
+
+        if *no_wallet && WALLET_METHODS.contains(&probe.0) {
+            self.record_present(probe, Ok(false));
+        } else {
+            self.record(probe, outcome);
+        }
+    }
+
+    fn record_present(
+        &mut self,
+        (target, severity, description): (&str, Severity, &str),
+        present: Result<bool, CallError>,
+    ) {
+        self.probes += 1;
+
+        match present {
+            Ok(true) => self.findings.push(Finding {
+                target: target.to_string(),
+                severity,
+                description: description.to_string(),
+            }),
+            Ok(false) => {}
+            Err(e) => {
+                self.failures += 1;
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// The endpoint counts as unreachable only if every probe failed. When
+    /// only some failed, the findings are kept but the error says the audit
+    /// is incomplete.
+    fn into_result(mut self, protocol: &str, rpc: &str) -> CheckResult {
+        if self.failures == self.probes {
+            return CheckResult {
+                protocol: protocol.to_string(),
+                rpc: rpc.to_string(),
+                reachable: false,
+                result: None,
+                error: self.error.map(|e| e.to_string()),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
+            };
+        }
+
+        self.findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+
+        let error = self.error.map(|e| {
+            format!(
+                "Audit incomplete: {} of {} probes failed, last with: {}",
+                self.failures, self.probes, e
+            )
+        });
+
+        CheckResult {
+            protocol: protocol.to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Audit {
+                findings: self.findings,
+            }),
+            error,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        }
+    }
+}
+
+/// Bitcoin Core answers wallet methods with `-18` when no wallet is loaded,
+/// so there is nothing for them to expose.
+fn no_wallet_loaded(err: &CallError) -> bool {
+    match err {
+        CallError::Rpc { code, message, .. } => {
+            *code == -18 || message.to_lowercase().contains("no wallet is loaded")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refusals_that_are_not_about_the_method() {
+        let mut audit = Audit::default();
+        let probe = ETHEREUM_METHODS[0];
+
+        audit.record(
+            probe,
+            Err(CallError::Rpc {
+                status: StatusCode::OK,
+                code: -32601,
+                message: "the method admin_nodeInfo does not exist/is not available".to_string(),
+            }),
+        );
+        assert_eq!(audit.failures, 0);
+
+        audit.record(
+            probe,
+            Err(CallError::Rpc {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                code: -32005,
+                message: "rate limit exceeded".to_string(),
+            }),
+        );
+        audit.record(probe, Err(CallError::Http(StatusCode::FORBIDDEN)));
+        audit.record(probe, Err(CallError::Http(StatusCode::NOT_FOUND)));
+
+        assert!(audit.findings.is_empty());
+        assert_eq!(audit.probes, 4);
+        assert_eq!(audit.failures, 3);
+    }
+
+    fn rpc_error(code: i64, message: &str) -> Result<serde_json::Value, CallError> {
+        Err(CallError::Rpc {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code,
+            message: message.to_string(),
+        })
+    }
+
+    #[test]
+    fn partly_failed_audit() {
+        let mut audit = Audit::default();
+        audit.record(ETHEREUM_METHODS[0], Ok(serde_json::json!({})));
+        audit.record(ETHEREUM_METHODS[1], Err(CallError::Timeout));
+        audit.record(ETHEREUM_METHODS[2], rpc_error(-32601, "method not found"));
+
+        let result = audit.into_result("ethereum", "http://localhost:8545");
+        assert!(result.reachable);
+        assert!(matches!(
+            &result.result,
+            Some(ResultData::Audit { findings }) if findings.len() == 1
+        ));
+        assert_eq!(
+            result.error.as_deref(),
+            Some(
+                "Audit incomplete: 1 of 3 probes failed, last with: Timed out waiting for response"
+            )
+        );
+
+        let mut audit = Audit::default();
+        audit.record(ETHEREUM_METHODS[0], Err(CallError::Timeout));
+        let result = audit.into_result("ethereum", "http://localhost:8545");
+        assert!(!result.reachable);
+        assert!(result.result.is_none());
+    }
+
+    #[test]
+    fn no_wallet_is_not_exposed() {
+        let dumpprivkey = BITCOIN_METHODS
+            .iter()
+            .copied()
+            .find(|p| p.0 == "dumpprivkey")
+            .unwrap();
+        let usage = "dumpprivkey \"address\"\n\nReveals the private key";
+
+        let mut audit = Audit::default();
+        let mut no_wallet = false;
+        audit.record_bitcoin(
+            BITCOIN_METHODS[0],
+            rpc_error(-18, "No wallet is loaded. Load a wallet using loadwallet or create a new one with createwallet."),
+            &mut no_wallet,
+        );
+        audit.record_bitcoin(dumpprivkey, rpc_error(-1, usage), &mut no_wallet);
+        assert!(audit.findings.is_empty());
+        assert_eq!(audit.failures, 0);
+
+        let mut audit = Audit::default();
+        let mut no_wallet = false;
+        audit.record_bitcoin(
+            BITCOIN_METHODS[0],
+            Ok(serde_json::json!({"walletname": ""})),
+            &mut no_wallet,
+        );
+        audit.record_bitcoin(dumpprivkey, rpc_error(-1, usage), &mut no_wallet);
+        assert_eq!(audit.findings.len(), 2);
+        assert!(audit
+            .findings
+            .iter()
+            .all(|f| f.severity == Severity::Critical));
+    }
+}
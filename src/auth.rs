@@ -0,0 +1,110 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use sha2::Sha256;
+
+/// Credentials sent with every request: fixed headers (`Authorization: Bearer`,
+/// `x-api-key`, ...) and optionally an engine-API JWT.
+#[derive(Debug, Clone, Default)]
+pub struct Auth {
+    pub headers: Vec<(String, String)>,
+    /// Shared secret for engine-API style HS256 tokens.
+    pub jwt_secret: Option<Vec<u8>>,
+}
+
+impl Auth {
+    /// Builds credentials from `Name: value` header strings and the path of a
+    /// hex-encoded JWT secret (the `jwt.hex` file execution clients write).
+    pub fn new(headers: &[String], jwt_secret: Option<&str>) -> Result<Auth, String> {
+        let headers = headers
+            .iter()
+            .map(|h| parse_header(h))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let jwt_secret = match jwt_secret {
+            Some(path) => Some(read_jwt_secret(path)?),
+            None => None,
+        };
+
+        Ok(Auth {
+            headers,
+            jwt_secret,
+        })
+    }
+
+    /// Headers for one request. The JWT is issued afresh each time since
+    /// clients reject tokens whose `iat` is more than 60 seconds off.
+    pub fn header_map(&self) -> HeaderMap {
+        let mut map = HeaderMap::new();
+
+        for (name, value) in &self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                map.append(name, value);
+            }
+        }
+
+        if let Some(secret) = &self.jwt_secret {
+            if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", jwt(secret))) {
+                map.insert(AUTHORIZATION, value);
+            }
+        }
+
+        map
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid header: {} (expected Name: value)", header))?;
+    let (name, value) = (name.trim(), value.trim());
+
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("Invalid header name: {}", name))?;
+    HeaderValue::from_str(value).map_err(|_| format!("Invalid header value for {}", name))?;
+
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn read_jwt_secret(path: &str) -> Result<Vec<u8>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let contents = contents.trim();
+
+    let secret = hex::decode(contents.strip_prefix("0x").unwrap_or(contents))
+        .map_err(|e| format!("{}: invalid hex secret: {}", path, e))?;
+
+    if secret.len() != 32 {
+        return Err(format!(
+            "{}: JWT secret must be 32 bytes, got {}",
+            path,
+            secret.len()
+        ));
+    }
+
+    Ok(secret)
+}
+
+/// HS256 token carrying only the `iat` claim, as the engine API expects.
+fn jwt(secret: &[u8]) -> String {
+    let iat = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+    let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"iat":{}}}"#, iat));
+    let signing_input = format!("{}.{}", header, claims);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(signing_input.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+    format!("{}.{}", signing_input, signature)
+}
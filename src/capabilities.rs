@@ -0,0 +1,136 @@
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::jsonrpc::CallError;
+use crate::rpc::ZERO_ADDRESS;
+use crate::transport::Transport;
+use crate::types::{CheckResult, MethodSupport, ResultData, Support};
+
+/// Methods probed by `capabilities`, with parameters that are cheap to serve
+/// and never change node state.
+fn ethereum_methods() -> Vec<(&'static str, Value)> {
+    vec![
+        ("web3_clientVersion", json!([])),
+        ("net_version", json!([])),
+        ("net_peerCount", json!([])),
+        ("eth_chainId", json!([])),
+        ("eth_blockNumber", json!([])),
+        ("eth_syncing", json!([])),
+        ("eth_gasPrice", json!([])),
+        ("eth_maxPriorityFeePerGas", json!([])),
+        ("eth_feeHistory", json!(["0x1", "latest", []])),
+        ("eth_getBlockByNumber", json!(["latest", false])),
+        ("eth_getBlockReceipts", json!(["latest"])),
+        ("eth_getBalance", json!([ZERO_ADDRESS, "latest"])),
+        ("eth_getCode", json!([ZERO_ADDRESS, "latest"])),
+        ("eth_getTransactionCount", json!([ZERO_ADDRESS, "latest"])),
+        ("eth_getProof", json!([ZERO_ADDRESS, [], "latest"])),
+        (
+            "eth_call",
+            json!([{ "to": ZERO_ADDRESS, "data": "0x" }, "latest"]),
+        ),
+        ("eth_estimateGas", json!([{ "to": ZERO_ADDRESS }])),
+        (
+            "eth_createAccessList",
+            json!([{ "to": ZERO_ADDRESS }, "latest"]),
+        ),
+        (
+            "eth_getLogs",
+            json!([{ "fromBlock": "latest", "toBlock": "latest" }]),
+        ),
+        ("debug_getRawHeader", json!(["latest"])),
+        ("debug_traceBlockByNumber", json!(["0x0", {}])),
+        ("trace_block", json!(["0x0"])),
+        ("txpool_status", json!([])),
+    ]
+}
+
+pub async fn check_ethereum_capabilities(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let mut methods = Vec::new();
+    let mut unreachable = None;
+
+    let calls = ethereum_methods();
+    let names: Vec<&str> = calls.iter().map(|(method, _)| *method).collect();
+    let outcomes = transport.batch(calls).await;
+
+    for (method, outcome) in names.into_iter().zip(outcomes) {
+        let (support, message) = match outcome {
+            Ok(_) => (Support::Supported, None),
+            Err(e) => {
+                let support = classify(&e);
+                if support == Support::Error && unreachable.is_none() {
+                    unreachable = Some(e.to_string());
+                }
+                (support, Some(e.to_string()))
+            }
+        };
+
+        methods.push(MethodSupport {
+            method: method.to_string(),
+            support,
+            message,
+        });
+    }
+
+    let reachable = methods.iter().any(|m| m.support != Support::Error);
+
+    CheckResult {
+        protocol: "ethereum".to_string(),
+        rpc: rpc.to_string(),
+        reachable,
+        result: Some(ResultData::Capabilities {
+            methods,
+            batching: transport.batching(),
+        }),
+        error: if reachable { None } else { unreachable },
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
+
+/// Maps a failed call onto the support matrix. Any JSON-RPC error that is not
+/// about access or an unknown method means the method exists and merely
+/// rejected our parameters or state.
+pub(crate) fn classify(err: &CallError) -> Support {
+    if err.is_rate_limited() {
+        return Support::RateLimited;
+    }
+
+    match err {
+        CallError::Http(status) => match *status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Support::Forbidden,
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => Support::NotFound,
+            _ => Support::Error,
+        },
+        CallError::Rpc {
+            status,
+            code,
+            message,
+        } => {
+            let message = message.to_lowercase();
+
+            if *status == StatusCode::UNAUTHORIZED
+                || *status == StatusCode::FORBIDDEN
+                || message.contains("not allowed")
+                || message.contains("forbidden")
+                || message.contains("unauthorized")
+                || message.contains("not whitelisted")
+                || message.contains("disabled")
+            {
+                Support::Forbidden
+            } else if *code == -32601
+                || message.contains("does not exist")
+                || message.contains("method not found")
+                || message.contains("unsupported method")
+                || message.contains("not supported")
+            {
+                Support::NotFound
+            } else {
+                Support::Supported
+            }
+        }
+        CallError::Request(_) | CallError::Timeout | CallError::InvalidJson(_) => Support::Error,
+    }
+}
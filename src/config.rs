@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::types::{Command, Method, Protocol};
+
+/// Files looked for in the working directory when no `--config` is given.
+pub const DEFAULT_FILES: &[&str] = &["rpc-checker.toml", "rpc-checker.yaml", "rpc-checker.yml"];
+
+/// A configuration file: named endpoint profiles, written as TOML or YAML.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    text: String,
+}
+
+/// One endpoint and how to check it. String values may refer to environment
+/// variables as `${NAME}` or `${NAME:-default}`, expanded by
+/// [`Config::profile`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub protocol: Protocol,
+    pub url: String,
+    /// Check run when `--method` is not given.
+    pub method: Option<Method>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Path of a hex-encoded engine-API JWT secret.
+    pub jwt_secret: Option<String>,
+    /// Proxy for this endpoint, overriding `--proxy` and the environment.
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub thresholds: Thresholds,
+    /// Copied into the endpoint's results, e.g. network or provider.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    /// Overrides `--tls-warn-days`.
+    pub tls_warn_days: Option<i64>,
+}
+
+impl Config {
+    /// Reads a TOML file, or YAML if the name ends in `.yaml` or `.yml`.
+    /// Errors name the file and the offending line.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+
+        let parsed: Result<Config, String> = if is_yaml(path) {
+            serde_yaml::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&text).map_err(|e| e.to_string())
+        };
+
+        let mut config = parsed.map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        config.path = path.to_path_buf();
+        config.text = text;
+
+        // Checked against the protocol here, so an unused profile with a
+        // method its protocol lacks is still reported.
+        for (name, profile) in &config.profiles {
+            let Some(method) = profile.method else {
+                continue;
+            };
+            if Command::parse(profile.protocol, method, None, Duration::ZERO).is_none() {
+                let line = config.find_line(name, |l| is_key(l, "method"));
+                return Err(config.error(
+                    name,
+                    line,
+                    format!(
+                        "unsupported method {} for protocol {:?}",
+                        method, profile.protocol
+                    ),
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// The first of [`DEFAULT_FILES`] present in the working directory.
+    pub fn find() -> Option<PathBuf> {
+        DEFAULT_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+    }
+
+    /// The profile called `name` with its environment variables expanded.
+    /// Only selected profiles need their variables set.
+    pub fn profile(&self, name: &str) -> Result<Profile, String> {
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(|k| k.as_str()).collect();
+            format!(
+                "Unknown profile {}; the config defines: {}",
+                name,
+                known.join(", ")
+            )
+        })?;
+
+        let expand = |value: &String| {
+            interpolate(value).map_err(|(variable, e)| {
+                // The profile's first reference to the variable is the likely culprit.
+                let reference = format!("${{{}", variable);
+                let line = self.find_line(name, |l| l.contains(&reference));
+                self.error(name, line, e)
+            })
+        };
+        let expand_map = |map: &BTreeMap<String, String>| {
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), expand(v)?)))
+                .collect::<Result<BTreeMap<_, _>, String>>()
+        };
+
+        Ok(Profile {
+            protocol: profile.protocol,
+            url: expand(&profile.url)?,
+            method: profile.method,
+            headers: expand_map(&profile.headers)?,
+            jwt_secret: profile.jwt_secret.as_ref().map(expand).transpose()?,
+            proxy: profile.proxy.as_ref().map(expand).transpose()?,
+            thresholds: profile.thresholds.clone(),
+            labels: expand_map(&profile.labels)?,
+        })
+    }
+
+    /// The number of the first line defining profile `name` that matches.
+    fn find_line(&self, name: &str, matches: impl Fn(&str) -> bool) -> Option<usize> {
+        let lines = if is_yaml(&self.path) {
+            yaml_profile_lines(&self.text, name)
+        } else {
+            toml_profile_lines(&self.text, name)
+        };
+        lines.into_iter().find(|(_, l)| matches(l)).map(|(n, _)| n)
+    }
+
+    fn error(&self, name: &str, line: Option<usize>, e: String) -> String {
+        let line = line.map(|n| format!(" line {}:", n)).unwrap_or_default();
+        format!(
+            "Invalid config {}:{} profile {}: {}",
+            self.path.display(),
+            line,
+            name,
+            e
+        )
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    )
+}
+
+/// Whether `line` assigns `key`, as `key = ...` or `key: ...`.
+fn is_key(line: &str, key: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(key)
+        .is_some_and(|rest| rest.trim_start().starts_with(['=', ':']))
+}
+
+/// The numbered lines of a TOML file that belong to profile `name`: those
+/// under its `[profiles.<name>...]` tables and its keys in other tables.
+fn toml_profile_lines<'a>(text: &'a str, name: &str) -> Vec<(usize, &'a str)> {
+    // Quotes dropped, so `profiles."a"` and `profiles.a` compare equal.
+    let key = |raw: &str| -> String {
+        raw.chars()
+            .filter(|c| !matches!(c, '"' | '\'' | ' ' | '\t'))
+            .collect()
+    };
+    let profile = format!("profiles.{}", name);
+    let belongs = |path: &str| path == profile || path.starts_with(&format!("{}.", profile));
+
+    let mut table = String::new();
+    let mut found = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let path = if let Some(header) = trimmed.strip_prefix('[') {
+            table = key(header.split(']').next().unwrap_or_default());
+            table.clone()
+        } else if let Some((k, _)) = trimmed.split_once('=') {
+            match table.as_str() {
+                "" => key(k),
+                _ => format!("{}.{}", table, key(k)),
+            }
+        } else {
+            table.clone()
+        };
+        if belongs(&path) {
+            found.push((i + 1, line));
+        }
+    }
+    found
+}
+
+/// The numbered lines of a YAML file that belong to profile `name`: its key
+/// under `profiles:` and everything indented beneath it.
+fn yaml_profile_lines<'a>(text: &'a str, name: &str) -> Vec<(usize, &'a str)> {
+    let mut in_profiles = false;
+    let mut profile_indent = None;
+    let mut found = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent == 0 {
+            in_profiles = is_key(trimmed, "profiles");
+            profile_indent = None;
+            continue;
+        }
+        match profile_indent {
+            Some(depth) if indent > depth => found.push((i + 1, line)),
+            _ => {
+                let key = trimmed
+                    .split_once(':')
+                    .map(|(k, _)| k.trim().trim_matches(['"', '\'']));
+                profile_indent = (in_profiles && key == Some(name)).then_some(indent);
+                if profile_indent.is_some() {
+                    found.push((i + 1, line));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Expands `${NAME}` and `${NAME:-default}`. An empty variable counts as
+/// unset. Errors carry the variable's name.
+fn interpolate(raw: &str) -> Result<String, (String, String)> {
+    let mut out = String::new();
+    let mut rest = raw;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}').map(|i| start + i) else {
+            let variable = rest[start + 2..].to_string();
+            return Err((variable, format!("unterminated ${{ in {:?}", raw)));
+        };
+
+        let reference = &rest[start + 2..end];
+        let (variable, default) = match reference.split_once(":-") {
+            Some((variable, default)) => (variable, Some(default)),
+            None => (reference, None),
+        };
+
+        match (env::var(variable).ok().filter(|v| !v.is_empty()), default) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {
+                return Err((
+                    variable.to_string(),
+                    format!("environment variable {} is not set", variable),
+                ))
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
@@ -0,0 +1,184 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+
+use tower::{Layer, Service};
+
+use crate::auth::Auth;
+use crate::proxy::Route;
+use crate::tls::Trust;
+use crate::types::{CheckResult, Timings};
+
+/// How the shared HTTP client is set up.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Idle connections kept open per host.
+    pub pool_size: usize,
+    /// How long an idle pooled connection is kept; `None` closes it after
+    /// each request.
+    pub keep_alive: Option<Duration>,
+    /// Speak HTTP/2 from the start instead of HTTP/1.1 (h2c over plain HTTP).
+    pub http2: bool,
+    pub user_agent: String,
+    pub proxy: Route,
+    pub trust: Trust,
+}
+
+impl Default for ClientConfig {
+    fn default() -> ClientConfig {
+        ClientConfig {
+            pool_size: 8,
+            keep_alive: Some(Duration::from_secs(90)),
+            http2: false,
+            user_agent: format!("rpc-checker/{}", env!("CARGO_PKG_VERSION")),
+            proxy: Route::Env,
+            trust: Trust::default(),
+        }
+    }
+}
+
+/// Everything a check needs to reach endpoints: one HTTP client whose
+/// connection pool is shared by all checks, the credentials to send, the
+/// proxy to go through and the TLS trust settings. Cloning is cheap and
+/// shares the pool.
+#[derive(Debug, Clone)]
+pub struct Context {
+    pub client: reqwest::Client,
+    pub auth: Auth,
+    /// Also applied to websockets and TLS inspection, which don't use the
+    /// HTTP client.
+    pub proxy: Route,
+    pub trust: Trust,
+    /// TLS configuration built from `trust`, shared with websockets.
+    pub tls: Arc<rustls::ClientConfig>,
+}
+
+impl Context {
+    pub fn new(config: &ClientConfig, auth: Auth) -> Result<Context, String> {
+        // The client asks the route for every request, so HTTP follows the
+        // same proxy rules as websockets.
+        let route = config.proxy.clone();
+        let tls = config.trust.client_config()?;
+        let mut builder = reqwest::Client::builder()
+            .use_preconfigured_tls(tls.clone())
+            .user_agent(&config.user_agent)
+            .proxy(reqwest::Proxy::custom(move |url| {
+                route.proxy_for(url.as_str()).map(|p| p.url().clone())
+            }))
+            .connector_layer(CountConnections);
+
+        builder = match config.keep_alive {
+            Some(idle) => builder
+                .pool_max_idle_per_host(config.pool_size)
+                .pool_idle_timeout(idle),
+            None => builder.pool_max_idle_per_host(0),
+        };
+        if config.http2 {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| format!("Cannot set up the HTTP client: {}", e))?;
+
+        Ok(Context {
+            client,
+            auth,
+            proxy: config.proxy.clone(),
+            trust: config.trust.clone(),
+            tls: Arc::new(tls),
+        })
+    }
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::new(&ClientConfig::default(), Auth::default())
+            .expect("the default HTTP client is valid")
+    }
+}
+
+/// Requests and connections made by the check currently running on a task.
+#[derive(Default)]
+struct Usage {
+    requests: AtomicU32,
+    connections: AtomicU32,
+}
+
+tokio::task_local! {
+    static USAGE: Usage;
+}
+
+/// Runs a check, filling in its `timings`. Counting is per check even when
+/// many share the client, since each check runs as its own future.
+pub(crate) async fn measure(check: impl Future<Output = CheckResult>) -> CheckResult {
+    USAGE
+        .scope(Usage::default(), async {
+            let started = Instant::now();
+            let mut result = check.await;
+
+            let (requests, connections) = USAGE.with(|u| {
+                (
+                    u.requests.load(Ordering::Relaxed),
+                    u.connections.load(Ordering::Relaxed),
+                )
+            });
+
+            result.timings = Some(Timings {
+                elapsed_ms: started.elapsed().as_millis() as u64,
+                requests,
+                connections,
+                reused_connections: requests.saturating_sub(connections),
+            });
+            result
+        })
+        .await
+}
+
+pub(crate) fn count_request() {
+    let _ = USAGE.try_with(|u| u.requests.fetch_add(1, Ordering::Relaxed));
+}
+
+pub(crate) fn count_connection() {
+    let _ = USAGE.try_with(|u| u.connections.fetch_add(1, Ordering::Relaxed));
+}
+
+/// Sends an HTTP request, counting it towards the running check.
+pub(crate) async fn send(request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+    count_request();
+    request.send().await
+}
+
+/// Connector layer counting the connections the client opens. The pool only
+/// calls the connector when it has no idle connection to reuse, and does so
+/// from the task that sent the request.
+#[derive(Clone)]
+struct CountConnections;
+
+impl<S> Layer<S> for CountConnections {
+    type Service = Counted<S>;
+
+    fn layer(&self, inner: S) -> Counted<S> {
+        Counted(inner)
+    }
+}
+
+#[derive(Clone)]
+struct Counted<S>(S);
+
+impl<S: Service<R>, R> Service<R> for Counted<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> S::Future {
+        count_connection();
+        self.0.call(request)
+    }
+}
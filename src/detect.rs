@@ -0,0 +1,232 @@
+use reqwest::header::WWW_AUTHENTICATE;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::context::{self, Context};
+use crate::jsonrpc::CallError;
+use crate::rpc;
+use crate::transport::Transport;
+use crate::types::{AutoMethod, CheckResult, Command, Protocol};
+
+/// What an endpoint was recognised as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fingerprint {
+    Tendermint,
+    Ethereum,
+    Bitcoin,
+    Solana,
+    /// An Ethereum consensus client's beacon API.
+    Beacon,
+}
+
+impl Fingerprint {
+    /// The protocol to check the endpoint as. Solana and beacon nodes are
+    /// recognised, but there are no checks for them.
+    pub fn protocol(self) -> Option<Protocol> {
+        match self {
+            Fingerprint::Tendermint => Some(Protocol::Tendermint),
+            Fingerprint::Ethereum => Some(Protocol::Ethereum),
+            Fingerprint::Bitcoin => Some(Protocol::Bitcoin),
+            Fingerprint::Solana | Fingerprint::Beacon => None,
+        }
+    }
+
+    /// Name reported as a result's `protocol`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Fingerprint::Tendermint => "tendermint",
+            Fingerprint::Ethereum => "ethereum",
+            Fingerprint::Bitcoin => "bitcoin",
+            Fingerprint::Solana => "solana",
+            Fingerprint::Beacon => "beacon",
+        }
+    }
+}
+
+/// Probe outcomes, kept to tell an endpoint that answered in an unfamiliar
+/// way from one that could not be reached at all.
+#[derive(Default)]
+struct Probes {
+    answered: bool,
+    failure: Option<CallError>,
+}
+
+impl Probes {
+    fn note<T>(&mut self, outcome: Result<T, CallError>) -> Option<T> {
+        match outcome {
+            Ok(v) => {
+                self.answered = true;
+                Some(v)
+            }
+            Err(e) => {
+                // Anything but a failed request means something answered.
+                if !matches!(e, CallError::Request(_) | CallError::Timeout) {
+                    self.answered = true;
+                }
+                self.failure = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// Works out what `rpc` is by trying, in turn, Ethereum's `eth_chainId`,
+/// Tendermint's `/status`, Bitcoin's `getblockchaininfo`, Solana's
+/// `getVersion`, a beacon node's `/eth/v1/node/version` and Ethereum's
+/// `web3_clientVersion`. Bitcoin and beacon nodes are only probed over HTTP.
+///
+/// Returns `None` when the endpoint answered but matched none of them, and
+/// the last failure when nothing answered at all.
+pub async fn detect(rpc: &str, ctx: &Context) -> Result<Option<Fingerprint>, CallError> {
+    let mut transport = Transport::connect(rpc, ctx).await?;
+    let mut probes = Probes::default();
+    let http = !transport.is_streaming();
+
+    let chain_id = probes.note(transport.call("eth_chainId", json!([])).await);
+    if chain_id.is_some_and(|v| v.as_str().is_some_and(|id| id.starts_with("0x"))) {
+        return Ok(Some(Fingerprint::Ethereum));
+    }
+
+    let status = probes.note(transport.route("status", &[]).await);
+    if status.is_some_and(|v| v["node_info"].is_object() && v["sync_info"].is_object()) {
+        return Ok(Some(Fingerprint::Tendermint));
+    }
+
+    if http && is_bitcoin(rpc, ctx, &mut probes).await {
+        return Ok(Some(Fingerprint::Bitcoin));
+    }
+
+    let version = probes.note(transport.call("getVersion", json!([])).await);
+    if version.is_some_and(|v| v["solana-core"].is_string()) {
+        return Ok(Some(Fingerprint::Solana));
+    }
+
+    if http {
+        let body = transport.get_text("eth/v1/node/version").await;
+        let version = probes
+            .note(body)
+            .and_then(|body| serde_json::from_str::<Value>(&body).ok());
+        if version.is_some_and(|v| v["data"]["version"].is_string()) {
+            return Ok(Some(Fingerprint::Beacon));
+        }
+    }
+
+    let client = probes.note(transport.call("web3_clientVersion", json!([])).await);
+    if client.is_some_and(|v| v.is_string()) {
+        return Ok(Some(Fingerprint::Ethereum));
+    }
+
+    match probes.failure {
+        Some(e) if !probes.answered => Err(e),
+        _ => Ok(None),
+    }
+}
+
+/// Asks for `getblockchaininfo` the way the Bitcoin checks do. Bitcoin Core
+/// turns away wrong credentials with its own `jsonrpc` realm, which is
+/// recognisable too.
+async fn is_bitcoin(rpc: &str, ctx: &Context, probes: &mut Probes) -> bool {
+    let req = json!({
+        "jsonrpc": "1.0",
+        "id": "rpc-checker",
+        "method": "getblockchaininfo",
+        "params": []
+    });
+
+    let response = context::send(
+        ctx.client
+            .post(rpc)
+            .basic_auth("rpcuser", Some("rpcpass"))
+            .headers(ctx.auth.header_map())
+            .json(&req),
+    )
+    .await;
+
+    let response = match response {
+        Ok(r) => r,
+        Err(e) => {
+            probes.note::<()>(Err(CallError::Request(e.to_string())));
+            return false;
+        }
+    };
+    probes.answered = true;
+
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("realm=\"jsonrpc\""));
+    }
+
+    let json: Value = response.json().await.unwrap_or_default();
+    json["result"]["chain"].is_string() && json["result"]["blocks"].is_u64()
+}
+
+/// Detects what `rpc` is, then runs the requested method on it as that
+/// protocol.
+pub(crate) async fn check(auto: AutoMethod, rpc: &str, ctx: &Context) -> CheckResult {
+    let fingerprint = match detect(rpc, ctx).await {
+        Ok(Some(f)) => f,
+        Ok(None) => return CheckResult {
+            protocol: "unknown".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: None,
+            error: Some(
+                "Unrecognised endpoint: not Tendermint, Ethereum, Bitcoin, Solana or a beacon node"
+                    .to_string(),
+            ),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+        Err(e) => {
+            return CheckResult {
+                protocol: "unknown".to_string(),
+                rpc: rpc.to_string(),
+                reachable: false,
+                result: None,
+                error: Some(e.to_string()),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
+            }
+        }
+    };
+
+    let cmd = fingerprint
+        .protocol()
+        .and_then(|p| Command::parse(p, auto.method, auto.height, auto.duration));
+
+    match cmd {
+        Some(cmd) => rpc::run(cmd, rpc, ctx).await,
+        None => {
+            let error = match fingerprint.protocol() {
+                Some(_) => format!(
+                    "Detected a {} endpoint, which has no {} check",
+                    fingerprint.name(),
+                    auto.method
+                ),
+                None => format!(
+                    "Detected a {} endpoint, which is not supported",
+                    fingerprint.name()
+                ),
+            };
+
+            CheckResult {
+                protocol: fingerprint.name().to_string(),
+                rpc: rpc.to_string(),
+                reachable: true,
+                result: None,
+                error: Some(error),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
+            }
+        }
+    }
+}
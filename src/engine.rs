@@ -0,0 +1,93 @@
+use serde_json::{json, Value};
+
+use crate::jsonrpc::CallError;
+use crate::transport::Transport;
+use crate::types::{CheckResult, ResultData};
+
+/// Engine methods a current consensus client offers in
+/// `engine_exchangeCapabilities`. The execution client answers with its own
+/// list, which is what gets reported.
+const CONSENSUS_CAPABILITIES: &[&str] = &[
+    "engine_newPayloadV1",
+    "engine_newPayloadV2",
+    "engine_newPayloadV3",
+    "engine_newPayloadV4",
+    "engine_forkchoiceUpdatedV1",
+    "engine_forkchoiceUpdatedV2",
+    "engine_forkchoiceUpdatedV3",
+    "engine_getPayloadV1",
+    "engine_getPayloadV2",
+    "engine_getPayloadV3",
+    "engine_getPayloadV4",
+    "engine_getPayloadBodiesByHashV1",
+    "engine_getPayloadBodiesByRangeV1",
+    "engine_getClientVersionV1",
+    "engine_getBlobsV1",
+];
+
+pub async fn check_engine_status(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let capabilities = match transport
+        .call(
+            "engine_exchangeCapabilities",
+            json!([CONSENSUS_CAPABILITIES]),
+        )
+        .await
+    {
+        Ok(v) => {
+            let mut methods: Vec<String> = v
+                .as_array()
+                .map(|methods| {
+                    methods
+                        .iter()
+                        .filter_map(|m| m.as_str().map(|s| s.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            methods.sort();
+            methods
+        }
+        Err(e) => return engine_error(rpc, e),
+    };
+
+    // `false` once synced, otherwise an object with the sync progress.
+    let sync = match transport.call("eth_syncing", json!([])).await {
+        Ok(v) => v,
+        Err(e) => return engine_error(rpc, e),
+    };
+
+    CheckResult {
+        protocol: "engine".to_string(),
+        rpc: rpc.to_string(),
+        reachable: true,
+        result: Some(ResultData::Engine {
+            capabilities,
+            syncing: sync.is_object(),
+            current_block: parse_hex(&sync["currentBlock"]),
+            highest_block: parse_hex(&sync["highestBlock"]),
+        }),
+        error: None,
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
+
+fn engine_error(rpc: &str, err: CallError) -> CheckResult {
+    CheckResult {
+        protocol: "engine".to_string(),
+        rpc: rpc.to_string(),
+        reachable: false,
+        result: None,
+        error: Some(err.to_string()),
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
+
+fn parse_hex(v: &Value) -> Option<u64> {
+    v.as_str()
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::auth::Auth;
+use crate::context;
+
+#[derive(Debug, Clone)]
+pub enum CallError {
+    Request(String),
+    /// No response arrived in time (websocket calls and notifications).
+    Timeout,
+    Http(StatusCode),
+    InvalidJson(String),
+    Rpc {
+        status: StatusCode,
+        code: i64,
+        message: String,
+    },
+}
+
+impl CallError {
+    /// Message reported by the node, if the response carried a JSON-RPC error.
+    pub fn rpc_message(&self) -> Option<&str> {
+        match self {
+            CallError::Rpc { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Whether the provider throttled us, either by HTTP status or by the
+    /// conventional `-32005` / "rate limit" JSON-RPC errors.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            CallError::Http(status) => *status == StatusCode::TOO_MANY_REQUESTS,
+            CallError::Rpc {
+                status,
+                code,
+                message,
+            } => {
+                let message = message.to_lowercase();
+
+                *status == StatusCode::TOO_MANY_REQUESTS
+                    || *code == -32005
+                    || message.contains("rate limit")
+                    || message.contains("too many requests")
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Request(e) => write!(f, "{}", e),
+            CallError::Timeout => write!(f, "Timed out waiting for response"),
+            CallError::Http(status) => write!(f, "HTTP {}", status),
+            CallError::InvalidJson(e) => write!(f, "Invalid JSON: {}", e),
+            CallError::Rpc { code, message, .. } => write!(f, "RPC error {}: {}", code, message),
+        }
+    }
+}
+
+/// Builds a JSON-RPC 2.0 request envelope.
+pub fn request(method: &str, params: Value, id: u64) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": id
+    })
+}
+
+/// Sends a JSON-RPC 2.0 request and returns its `result` member.
+pub async fn call(
+    client: &reqwest::Client,
+    rpc: &str,
+    auth: &Auth,
+    method: &str,
+    params: Value,
+) -> Result<Value, CallError> {
+    let req = request(method, params, 1);
+
+    let response = context::send(client.post(rpc).headers(auth.header_map()).json(&req))
+        .await
+        .map_err(|e| CallError::Request(e.to_string()))?;
+
+    parse_response(response).await
+}
+
+/// Sends a Bitcoin Core style JSON-RPC 1.0 request and returns its `result`
+/// member. An `Authorization` header in `auth` replaces the default
+/// credentials.
+pub async fn call_bitcoin(
+    client: &reqwest::Client,
+    rpc: &str,
+    auth: &Auth,
+    method: &str,
+    params: Value,
+) -> Result<Value, CallError> {
+    let req = serde_json::json!({
+        "jsonrpc": "1.0",
+        "id": "rpc-checker",
+        "method": method,
+        "params": params
+    });
+
+    let response = context::send(
+        client
+            .post(rpc)
+            .basic_auth("rpcuser", Some("rpcpass"))
+            .headers(auth.header_map())
+            .json(&req),
+    )
+    .await
+    .map_err(|e| CallError::Request(e.to_string()))?;
+
+    parse_response(response).await
+}
+
+/// Outcome of sending several calls as one JSON-RPC batch.
+pub enum Batch {
+    /// One result per call, in the order the calls were given.
+    Answered(Vec<Result<Value, CallError>>),
+    /// The server does not take batches; send the calls one at a time.
+    Unsupported,
+}
+
+/// Posts `calls` (request envelopes with distinct ids) as a single batch.
+/// Errors that would fail the calls just as well one by one, such as an
+/// unreachable endpoint or rate limiting, are returned as errors.
+pub async fn batch(request: reqwest::RequestBuilder, calls: &[Value]) -> Result<Batch, CallError> {
+    let response = context::send(request.json(calls))
+        .await
+        .map_err(|e| CallError::Request(e.to_string()))?;
+
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(CallError::Http(status));
+    }
+
+    let replies = match response.json::<Value>().await {
+        Ok(Value::Array(replies)) if !replies.is_empty() => replies,
+        // Typically a single `-32600` error object, or an HTTP 4xx.
+        Ok(other) => {
+            return match parse_envelope(status, other) {
+                Err(e) if e.is_rate_limited() => Err(e),
+                _ => Ok(Batch::Unsupported),
+            };
+        }
+        Err(_) => return Ok(Batch::Unsupported),
+    };
+
+    // Replies may come in any order; match them to calls by id.
+    let results = calls
+        .iter()
+        .map(
+            |call| match replies.iter().find(|r| r["id"] == call["id"]) {
+                Some(reply) => parse_envelope(status, reply.clone()),
+                None => Err(CallError::InvalidJson(format!(
+                    "No reply to {} in batch",
+                    call["method"]
+                ))),
+            },
+        )
+        .collect();
+
+    Ok(Batch::Answered(results))
+}
+
+/// Fetches a URI-style endpoint (e.g. Tendermint `/block?height=1`) and
+/// returns its `result` member.
+pub async fn get(client: &reqwest::Client, url: &str, auth: &Auth) -> Result<Value, CallError> {
+    let response = context::send(client.get(url).headers(auth.header_map()))
+        .await
+        .map_err(|e| CallError::Request(e.to_string()))?;
+
+    parse_response(response).await
+}
+
+async fn parse_response(response: reqwest::Response) -> Result<Value, CallError> {
+    let status = response.status();
+
+    let json: Value = match response.json().await {
+        Ok(v) => v,
+        Err(e) => {
+            if !status.is_success() {
+                return Err(CallError::Http(status));
+            }
+            return Err(CallError::InvalidJson(e.to_string()));
+        }
+    };
+
+    parse_envelope(status, json)
+}
+
+/// Extracts `result` from a decoded response, turning an `error` member or a
+/// failed HTTP status into a `CallError`.
+pub fn parse_envelope(status: StatusCode, json: Value) -> Result<Value, CallError> {
+    let error = &json["error"];
+    if error.is_object() {
+        let code = error["code"].as_i64().unwrap_or_default();
+        let mut message = error["message"].as_str().unwrap_or_default().to_string();
+        if let Some(data) = error["data"].as_str() {
+            message = format!("{}: {}", message, data);
+        }
+
+        return Err(CallError::Rpc {
+            status,
+            code,
+            message,
+        });
+    }
+
+    if !status.is_success() {
+        return Err(CallError::Http(status));
+    }
+
+    Ok(json["result"].clone())
+}
@@ -0,0 +1,19 @@
+mod audit;
+pub mod auth;
+mod capabilities;
+pub mod config;
+pub mod context;
+pub mod detect;
+mod engine;
+mod jsonrpc;
+mod logs;
+pub mod mock;
+pub mod output;
+pub mod proxy;
+pub mod record;
+pub mod redact;
+pub mod rpc;
+mod subscription;
+pub mod tls;
+mod transport;
+pub mod types;
@@ -0,0 +1,181 @@
+use serde_json::json;
+
+use crate::jsonrpc::CallError;
+use crate::rpc::ZERO_ADDRESS;
+use crate::transport::Transport;
+use crate::types::{CheckResult, LogsLimit, ResultData};
+
+/// Ranges tried before bisecting. Probing stops here: an endpoint that
+/// accepts 100k blocks is effectively unlimited for indexers.
+const RANGE_STEPS: &[u64] = &[1, 10, 100, 1_000, 10_000, 100_000];
+
+/// Stop growing the unfiltered query once a response holds this many logs,
+/// so busy chains do not turn the check into a multi-megabyte download.
+const RESULT_PROBE_CAP: u64 = 20_000;
+
+pub async fn check_ethereum_logs(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let latest_block = match transport.call("eth_blockNumber", json!([])).await {
+        Ok(v) => match v
+            .as_str()
+            .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        {
+            Some(h) => h,
+            None => return logs_error(rpc, "Missing latest block height".to_string()),
+        },
+        Err(e) => return logs_error(rpc, e.to_string()),
+    };
+
+    // A filter on the zero address matches nothing, so only the range cap can
+    // reject these queries.
+    let block_range = match probe_block_range(transport, latest_block).await {
+        Ok(limit) => limit,
+        Err(e) => return logs_error(rpc, e.to_string()),
+    };
+
+    let max_span = block_range.max_accepted.unwrap_or(1);
+    let results = match probe_results(transport, latest_block, max_span).await {
+        Ok(limit) => limit,
+        Err(e) => return logs_error(rpc, e.to_string()),
+    };
+
+    CheckResult {
+        protocol: "ethereum".to_string(),
+        rpc: rpc.to_string(),
+        reachable: true,
+        result: Some(ResultData::Logs {
+            latest_block,
+            block_range,
+            results,
+        }),
+        error: None,
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
+
+async fn probe_block_range(
+    transport: &mut Transport,
+    latest_block: u64,
+) -> Result<LogsLimit, CallError> {
+    let chain_length = latest_block + 1;
+
+    let mut accepted = None;
+    let mut rejected = None;
+    let mut error = None;
+
+    for &span in RANGE_STEPS {
+        let span = span.min(chain_length);
+
+        match get_logs(transport, latest_block, span, Some(ZERO_ADDRESS)).await? {
+            Ok(_) => accepted = Some(span),
+            Err(e) => {
+                rejected = Some(span);
+                error = Some(e.to_string());
+                break;
+            }
+        }
+
+        if span == chain_length {
+            break;
+        }
+    }
+
+    if let (Some(mut ok), Some(mut failed)) = (accepted, rejected) {
+        while failed - ok > 1 {
+            let mid = ok + (failed - ok) / 2;
+            match get_logs(transport, latest_block, mid, Some(ZERO_ADDRESS)).await? {
+                Ok(_) => ok = mid,
+                Err(e) => {
+                    failed = mid;
+                    error = Some(e.to_string());
+                }
+            }
+        }
+        accepted = Some(ok);
+    }
+
+    Ok(LogsLimit {
+        max_accepted: accepted,
+        limited: rejected.is_some(),
+        error,
+    })
+}
+
+async fn probe_results(
+    transport: &mut Transport,
+    latest_block: u64,
+    max_span: u64,
+) -> Result<LogsLimit, CallError> {
+    let mut accepted = None;
+    let mut error = None;
+    let mut span = 1;
+
+    loop {
+        match get_logs(transport, latest_block, span, None).await? {
+            Ok(count) => {
+                accepted = Some(accepted.unwrap_or(0).max(count));
+                if count >= RESULT_PROBE_CAP {
+                    break;
+                }
+            }
+            Err(e) => {
+                error = Some(e.to_string());
+                break;
+            }
+        }
+
+        if span >= max_span {
+            break;
+        }
+        span = (span * 2).min(max_span);
+    }
+
+    Ok(LogsLimit {
+        max_accepted: accepted,
+        limited: error.is_some(),
+        error,
+    })
+}
+
+/// Queries `eth_getLogs` over the `span` blocks ending at `latest_block`.
+/// The outer error aborts discovery (unreachable or throttled); the inner one
+/// is the provider rejecting this particular query.
+async fn get_logs(
+    transport: &mut Transport,
+    latest_block: u64,
+    span: u64,
+    address: Option<&str>,
+) -> Result<Result<u64, CallError>, CallError> {
+    let from = latest_block + 1 - span;
+
+    let mut filter = json!({
+        "fromBlock": format!("0x{:x}", from),
+        "toBlock": format!("0x{:x}", latest_block),
+    });
+    if let Some(address) = address {
+        filter["address"] = json!(address);
+    }
+
+    match transport.call("eth_getLogs", json!([filter])).await {
+        Ok(v) => Ok(Ok(v.as_array().map_or(0, |logs| logs.len() as u64))),
+        Err(e) if e.is_rate_limited() => Err(e),
+        Err(e @ CallError::Request(_)) if span == 1 => Err(e),
+        Err(e) => Ok(Err(e)),
+    }
+}
+
+fn logs_error(rpc: &str, error: String) -> CheckResult {
+    CheckResult {
+        protocol: "ethereum".to_string(),
+        rpc: rpc.to_string(),
+        reachable: false,
+        result: None,
+        error: Some(error),
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
@@ -1,63 +1,868 @@
-mod rpc;
-mod types;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::Parser;
-use rpc::check;
-use types::{BitcoinMethod, Command, EthereumMethod, Protocol, TendermintMethod};
+use clap::builder::RangedU64ValueParser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use futures_util::{stream, StreamExt};
+use rpc_checker::auth::Auth;
+use rpc_checker::config::Config;
+use rpc_checker::context::{ClientConfig, Context};
+use rpc_checker::mock::{MockServer, Scenario};
+use rpc_checker::output::{self, Format};
+use rpc_checker::proxy::{Proxy, Route};
+use rpc_checker::record::{self, Tape};
+use rpc_checker::redact::Redactor;
+use rpc_checker::rpc::check;
+use rpc_checker::tls::{self, Trust};
+use rpc_checker::types::{
+    self, AutoMethod, BitcoinMethod, CheckResult, Command, EngineMethod, EthereumMethod, Method,
+    Protocol, TendermintMethod,
+};
 
+/// Checks the health, history and capabilities of blockchain RPC endpoints.
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    #[command(subcommand)]
+    command: Cli,
+
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cli {
+    /// Check Tendermint / CometBFT RPC endpoints
+    Tendermint(TendermintArgs),
+
+    /// Check Ethereum JSON-RPC endpoints
+    Ethereum(EthereumArgs),
+
+    /// Check Bitcoin Core RPC endpoints
+    Bitcoin(BitcoinArgs),
+
+    /// Check an execution client's authenticated engine API
+    Engine(EngineArgs),
+
+    /// Detect what the endpoints are, then check them as that protocol
+    Auto(AutoArgs),
+
+    /// Check the endpoints of profiles from a config file
+    #[command(long_flag = "profile")]
+    Profile(ProfileArgs),
+
+    /// Print the JSON Schema of the result output
+    Schema,
+
+    /// Serve a mock node for offline testing
+    Mock {
+        protocol: Protocol,
+
+        /// Built-in scenario (healthy, syncing, stalled, erroring, slow, malformed) or a scenario file
+        #[arg(long, default_value = "healthy")]
+        scenario: String,
+
+        #[arg(long, default_value = "127.0.0.1:8545")]
+        listen: String,
+    },
+
+    /// Print a shell completion script
+    Completions { shell: Shell },
+
+    /// Print the man page
+    Man,
+}
+
+// Settings shared by every check, accepted before or after the subcommand.
+#[derive(clap::Args, Debug)]
+struct Options {
+    /// Warn when an HTTPS endpoint's certificate expires within this many days
+    #[arg(long, global = true, default_value_t = 14)]
+    tls_warn_days: i64,
+
+    /// Extra request header as `Name: value`, e.g. `Authorization: Bearer <token>`
+    /// or `x-api-key: <key>` (repeatable)
+    #[arg(long = "header", global = true, value_name = "NAME: VALUE")]
+    headers: Vec<String>,
+
+    /// Extra regex for path segments and query values to redact from output (repeatable)
+    #[arg(long = "redact-pattern", global = true, value_name = "REGEX")]
+    redact_patterns: Vec<String>,
+
+    /// Print endpoint URLs as given, including any credentials in them
+    #[arg(long, global = true)]
+    no_redact: bool,
+
+    /// Save every HTTP request and response made by the check under this directory
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer the check from a recording made with `--record` instead of the endpoint
+    #[arg(long, global = true, value_name = "DIR")]
+    replay: Option<PathBuf>,
+
+    /// Idle connections kept open per host between requests
+    #[arg(long, global = true, default_value_t = 8)]
+    pool_size: usize,
+
+    /// Seconds an idle connection is kept for reuse; 0 opens a new one per request
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 90)]
+    keep_alive: u64,
+
+    /// Use HTTP/2 from the first request (h2c on http:// endpoints)
+    #[arg(long, global = true)]
+    http2: bool,
+
+    /// User-Agent header sent with HTTP requests [default: rpc-checker/<version>]
+    #[arg(long, global = true)]
+    user_agent: Option<String>,
+
+    /// Proxy for every connection: http://, socks5:// or socks5h:// (DNS through
+    /// the proxy, as Tor needs) [default: from HTTP_PROXY, HTTPS_PROXY, ALL_PROXY]
+    #[arg(long, global = true, value_name = "URL", conflicts_with = "no_proxy")]
+    proxy: Option<String>,
+
+    /// Connect directly, ignoring the proxy environment variables
+    #[arg(long, global = true)]
+    no_proxy: bool,
+
+    /// Extra PEM bundle of CA certificates to trust (repeatable)
+    #[arg(long = "ca-cert", global = true, value_name = "PATH")]
+    ca_certs: Vec<PathBuf>,
+
+    /// PEM client certificate chain for mutual TLS
+    #[arg(long, global = true, value_name = "PATH", requires = "client_key")]
+    client_cert: Option<PathBuf>,
+
+    /// PEM private key for --client-cert
+    #[arg(long, global = true, value_name = "PATH", requires = "client_cert")]
+    client_key: Option<PathBuf>,
+
+    /// Require this public key in the certificate chain, as sha256//<base64> (repeatable)
+    #[arg(long = "pin", global = true, value_name = "PIN")]
+    pins: Vec<String>,
+
+    /// Skip certificate verification; results are marked `insecure`
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    /// How many endpoints to check at the same time
+    #[arg(long, global = true, default_value_t = 8, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    concurrency: usize,
+
+    #[arg(long, global = true, value_enum, default_value_t = Format::Json)]
+    output: Format,
+}
+
+/// The endpoints a check runs against.
+#[derive(clap::Args, Debug)]
+struct Endpoints {
+    /// Endpoint to check; repeat to check several in one run
+    #[arg(long, required = true)]
+    rpc: Vec<String>,
+
+    /// Hex-encoded secret for engine-API style JWT authentication (e.g. geth's jwt.hex)
+    #[arg(long, value_name = "PATH")]
+    jwt_secret: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct TendermintArgs {
+    method: TendermintCheck,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
+
+    /// Height for `block` [default: latest]
     #[arg(long)]
-    protocol: String,
+    height: Option<u64>,
+
+    /// How long `subscribe` holds the subscription, in seconds [default: 60]
+    #[arg(long, value_name = "SECS")]
+    duration: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TendermintCheck {
+    /// Latest height and whether the node is catching up
+    Status,
+    /// Whether /health answers
+    Health,
+    /// Fetch a block by height
+    Block,
+    /// Earliest block the node still serves
+    Depth,
+    /// Look for exposed unsafe routes
+    Audit,
+    /// Whether new blocks arrive over a websocket subscription
+    Subscription,
+    /// Measure block delivery over a subscription for --duration
+    Subscribe,
+}
+
+#[derive(clap::Args, Debug)]
+struct EthereumArgs {
+    method: EthereumCheck,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
 
+    /// Block for `block` and `archive` [default: latest and 1 respectively]
     #[arg(long)]
-    method: String,
+    height: Option<u64>,
 
+    /// How long `subscribe` holds the subscription, in seconds [default: 60]
+    #[arg(long, value_name = "SECS")]
+    duration: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum EthereumCheck {
+    /// Latest block and sync state
+    Status,
+    /// Whether eth_chainId answers
+    Health,
+    /// Fetch a block by number
+    Block,
+    /// Whether historical state is served (archive, full or pruned)
+    Archive,
+    /// Earliest block the node still serves
+    Depth,
+    /// Which common JSON-RPC methods are supported
+    Capabilities,
+    /// Block-range and result limits of eth_getLogs
+    Logs,
+    /// Look for exposed admin, debug and personal methods
+    Audit,
+    /// Whether new heads arrive over a websocket subscription
+    Subscription,
+    /// Measure new-head delivery over a subscription for --duration
+    Subscribe,
+}
+
+#[derive(clap::Args, Debug)]
+struct BitcoinArgs {
+    method: BitcoinCheck,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
+
+    /// Height for `block` [default: latest]
     #[arg(long)]
-    rpc: String,
+    height: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BitcoinCheck {
+    /// Latest block and whether the node is still syncing
+    Status,
+    /// Whether getnetworkinfo answers
+    Health,
+    /// Fetch a block by height
+    Block,
+    /// Earliest block the node still serves
+    Depth,
+    /// Look for exposed wallet and admin methods
+    Audit,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(mut_arg("jwt_secret", |arg| arg.required(true)))]
+struct EngineArgs {
+    method: EngineCheck,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum EngineCheck {
+    /// Supported engine methods and sync state
+    Status,
+}
+
+#[derive(clap::Args, Debug)]
+struct AutoArgs {
+    /// Check to run as the detected protocol
+    method: Method,
+
+    #[command(flatten)]
+    endpoints: Endpoints,
+
+    /// Height for `block` and `archive`
+    #[arg(long)]
+    height: Option<u64>,
+
+    /// How long `subscribe` holds the subscription, in seconds [default: 60]
+    #[arg(long, value_name = "SECS")]
+    duration: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ProfileArgs {
+    /// Profiles to check
+    #[arg(required = true, value_name = "NAME")]
+    names: Vec<String>,
+
+    /// Config file with endpoint profiles [default: rpc-checker.toml, .yaml or .yml]
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Check to run instead of the one each profile names
+    #[arg(long)]
+    method: Option<Method>,
+
+    /// Height for `block` and `archive`
+    #[arg(long)]
+    height: Option<u64>,
+
+    /// How long `subscribe` holds the subscription, in seconds [default: 60]
+    #[arg(long, value_name = "SECS")]
+    duration: Option<u64>,
+
+    /// JWT secret for profiles that don't name one
+    #[arg(long, value_name = "PATH")]
+    jwt_secret: Option<String>,
+}
+
+impl TendermintArgs {
+    fn command(&self) -> Result<Command, String> {
+        let cmd = Command::Tendermint(match self.method {
+            TendermintCheck::Status => TendermintMethod::Status,
+            TendermintCheck::Health => TendermintMethod::Health,
+            TendermintCheck::Block => TendermintMethod::Block {
+                height: self.height,
+            },
+            TendermintCheck::Depth => TendermintMethod::Depth,
+            TendermintCheck::Audit => TendermintMethod::Audit,
+            TendermintCheck::Subscription => TendermintMethod::Subscription,
+            TendermintCheck::Subscribe => TendermintMethod::Subscribe {
+                duration: subscribe_duration(self.duration),
+            },
+        });
+        unused_flags(&cmd, self.height, self.duration)?;
+        Ok(cmd)
+    }
+}
+
+impl EthereumArgs {
+    fn command(&self) -> Result<Command, String> {
+        let cmd = Command::Ethereum(match self.method {
+            EthereumCheck::Status => EthereumMethod::Status,
+            EthereumCheck::Health => EthereumMethod::Health,
+            EthereumCheck::Block => EthereumMethod::Block {
+                height: self.height,
+            },
+            EthereumCheck::Archive => EthereumMethod::Archive {
+                height: self.height,
+            },
+            EthereumCheck::Depth => EthereumMethod::Depth,
+            EthereumCheck::Capabilities => EthereumMethod::Capabilities,
+            EthereumCheck::Logs => EthereumMethod::Logs,
+            EthereumCheck::Audit => EthereumMethod::Audit,
+            EthereumCheck::Subscription => EthereumMethod::Subscription,
+            EthereumCheck::Subscribe => EthereumMethod::Subscribe {
+                duration: subscribe_duration(self.duration),
+            },
+        });
+        unused_flags(&cmd, self.height, self.duration)?;
+        Ok(cmd)
+    }
+}
+
+impl BitcoinArgs {
+    fn command(&self) -> Result<Command, String> {
+        let cmd = Command::Bitcoin(match self.method {
+            BitcoinCheck::Status => BitcoinMethod::Status,
+            BitcoinCheck::Health => BitcoinMethod::Health,
+            BitcoinCheck::Block => BitcoinMethod::Block {
+                height: self.height,
+            },
+            BitcoinCheck::Depth => BitcoinMethod::Depth,
+            BitcoinCheck::Audit => BitcoinMethod::Audit,
+        });
+        unused_flags(&cmd, self.height, None)?;
+        Ok(cmd)
+    }
+}
+
+impl EngineArgs {
+    fn command(&self) -> Command {
+        match self.method {
+            EngineCheck::Status => Command::Engine(EngineMethod::Status),
+        }
+    }
+}
+
+impl AutoArgs {
+    fn command(&self) -> Result<Command, String> {
+        let cmd = Command::Auto(AutoMethod {
+            method: self.method,
+            height: self.height,
+            duration: subscribe_duration(self.duration),
+        });
+        unused_flags(&cmd, self.height, self.duration)?;
+        Ok(cmd)
+    }
+}
+
+fn subscribe_duration(secs: Option<u64>) -> Duration {
+    Duration::from_secs(secs.unwrap_or(60))
+}
+
+/// Rejects `--height` and `--duration` on checks that would ignore them.
+fn unused_flags(cmd: &Command, height: Option<u64>, duration: Option<u64>) -> Result<(), String> {
+    let (takes_height, takes_duration) = match cmd {
+        Command::Auto(auto) => (
+            matches!(auto.method, Method::Block | Method::Archive),
+            auto.method == Method::Subscribe,
+        ),
+        cmd => (
+            matches!(
+                cmd,
+                Command::Tendermint(TendermintMethod::Block { .. })
+                    | Command::Ethereum(
+                        EthereumMethod::Block { .. } | EthereumMethod::Archive { .. }
+                    )
+                    | Command::Bitcoin(BitcoinMethod::Block { .. })
+            ),
+            matches!(
+                cmd,
+                Command::Tendermint(TendermintMethod::Subscribe { .. })
+                    | Command::Ethereum(EthereumMethod::Subscribe { .. })
+            ),
+        ),
+    };
+
+    if height.is_some() && !takes_height {
+        return Err("--height only applies to the block and archive checks".to_string());
+    }
+    if duration.is_some() && !takes_duration {
+        return Err("--duration only applies to the subscribe check".to_string());
+    }
+    Ok(())
+}
+
+/// Exits with a clap-style usage error.
+fn usage_error(message: String) -> ! {
+    Args::command()
+        .error(ErrorKind::ArgumentConflict, message)
+        .exit()
 }
 
-fn parse_protocol(p: &str) -> Protocol {
-    match p {
-        "tendermint" => Protocol::Tendermint,
-        "ethereum" => Protocol::Ethereum,
-        "bitcoin" => Protocol::Bitcoin,
-        _ => {
-            eprintln!("Unsupported protocol: {}", p);
+fn serve_mock(protocol: Protocol, scenario: &str, listen: &str) {
+    let scenario_name = scenario;
+    let scenario = match Scenario::load(scenario) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let server = match MockServer::start(protocol, scenario, listen) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Cannot listen on {}: {}", listen, e);
             std::process::exit(1);
         }
+    };
+
+    eprintln!(
+        "Mock {:?} node ({}) listening on {}",
+        protocol,
+        scenario_name,
+        server.url()
+    );
+    if let Some(ws) = server.ws_url() {
+        eprintln!("Websocket subscriptions on {}", ws);
     }
+    server.wait();
 }
 
-fn main() {
-    let args = Args::parse();
+/// What a run checks: one command against `--rpc` endpoints, or profiles.
+enum Run<'a> {
+    Endpoints(Result<Command, String>, &'a Endpoints),
+    Profiles(&'a ProfileArgs),
+}
+
+/// One endpoint to check and the settings to check it with.
+struct Target {
+    cmd: Command,
+    rpc: String,
+    ctx: Context,
+    tls_warn_days: i64,
+    labels: Option<BTreeMap<String, String>>,
+}
+
+/// Targets given with `--rpc`.
+fn endpoint_targets(
+    cmd: Command,
+    endpoints: &Endpoints,
+    options: &Options,
+    ctx: &Context,
+) -> Vec<Target> {
+    endpoints
+        .rpc
+        .iter()
+        .map(|rpc| Target {
+            cmd: cmd.clone(),
+            rpc: rpc.clone(),
+            ctx: ctx.clone(),
+            tls_warn_days: options.tls_warn_days,
+            labels: None,
+        })
+        .collect()
+}
+
+/// Targets named by config profiles. Command-line headers apply on top of
+/// each profile's; its JWT secret and proxy replace the command-line ones.
+fn profile_targets(
+    profiles: &ProfileArgs,
+    options: &Options,
+    client: &ClientConfig,
+    ctx: &Context,
+) -> Result<Vec<Target>, String> {
+    let path = profiles
+        .config
+        .clone()
+        .or_else(Config::find)
+        .ok_or("No config file found: pass --config or create rpc-checker.toml")?;
+    let config = Config::load(&path)?;
+
+    let mut targets = Vec::new();
+    for name in &profiles.names {
+        let profile = config.profile(name)?;
 
-    let _protocol = parse_protocol(&args.protocol);
+        let method = profiles
+            .method
+            .or(profile.method)
+            .ok_or_else(|| format!("Profile {} names no method; pass --method", name))?;
+        let cmd = Command::parse(
+            profile.protocol,
+            method,
+            profiles.height,
+            subscribe_duration(profiles.duration),
+        )
+        .ok_or_else(|| {
+            format!(
+                "Unsupported method {} for profile {} ({:?})",
+                method, name, profile.protocol
+            )
+        })?;
+        unused_flags(&cmd, profiles.height, profiles.duration)?;
 
-    let cmd = match (args.protocol.as_str(), args.method.as_str()) {
-        ("tendermint", "status") => Command::Tendermint(TendermintMethod::Status),
-        ("tendermint", "health") => Command::Tendermint(TendermintMethod::Health),
-        ("tendermint", "block") => Command::Tendermint(TendermintMethod::Block { height: None }),
+        let mut headers = options.headers.clone();
+        headers.extend(profile.headers.iter().map(|(k, v)| format!("{}: {}", k, v)));
+        let jwt_secret = profile
+            .jwt_secret
+            .as_deref()
+            .or(profiles.jwt_secret.as_deref());
+        let auth = Auth::new(&headers, jwt_secret)?;
 
-        ("ethereum", "status") => Command::Ethereum(EthereumMethod::Status),
-        ("ethereum", "health") => Command::Ethereum(EthereumMethod::Health),
-        ("ethereum", "block") => Command::Ethereum(EthereumMethod::Block { height: None }),
+        // Only a different proxy needs its own client; otherwise the
+        // connection pool stays shared.
+        let ctx = match &profile.proxy {
+            Some(proxy) => {
+                let config = ClientConfig {
+                    proxy: Route::Via(Proxy::parse(proxy)?),
+                    ..client.clone()
+                };
+                Context::new(&config, auth)?
+            }
+            None => Context {
+                auth,
+                ..ctx.clone()
+            },
+        };
 
-        ("bitcoin", "status") => Command::Bitcoin(BitcoinMethod::Status),
-        ("bitcoin", "health") => Command::Bitcoin(BitcoinMethod::Health),
-        ("bitcoin", "block") => Command::Bitcoin(BitcoinMethod::Block { height: None }),
+        targets.push(Target {
+            cmd,
+            rpc: profile.url.clone(),
+            ctx,
+            tls_warn_days: profile
+                .thresholds
+                .tls_warn_days
+                .unwrap_or(options.tls_warn_days),
+            labels: (!profile.labels.is_empty()).then(|| profile.labels.clone()),
+        });
+    }
+
+    Ok(targets)
+}
+
+/// Name reported as a result's `protocol` when the check could not run.
+fn protocol_name(cmd: &Command) -> &'static str {
+    match cmd {
+        Command::Tendermint(_) => "tendermint",
+        Command::Ethereum(_) => "ethereum",
+        Command::Bitcoin(_) => "bitcoin",
+        Command::Engine(_) => "engine",
+        Command::Auto(_) => "unknown",
+    }
+}
+
+/// Checks one endpoint, through a recording tape if asked to, and prepares
+/// its result for output.
+async fn check_target(options: &Options, target: &Target, redactor: &Redactor) -> CheckResult {
+    let (rpc, ctx) = (target.rpc.as_str(), &target.ctx);
+
+    let tape = match (&options.record, &options.replay) {
+        (Some(dir), _) => Some(Tape::record(
+            rpc,
+            &record::session_dir(dir, rpc, redactor),
+            ctx,
+            redactor,
+        )),
+        (_, Some(dir)) => Some(Tape::replay(&record::session_dir(dir, rpc, redactor))),
+        _ => None,
+    };
+    // A tape that cannot be opened fails this target only; the others in
+    // flight still finish and are reported.
+    let tape = match tape.transpose() {
+        Ok(t) => t,
+        Err(e) => {
+            let mut result = CheckResult {
+                protocol: protocol_name(&target.cmd).to_string(),
+                rpc: rpc.to_string(),
+                reachable: false,
+                result: None,
+                error: Some(e.to_string()),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: target.labels.clone(),
+            };
+            redactor.apply(&mut result);
+            return result;
+        }
+    };
+
+    let mut result = match &tape {
+        Some(tape) => {
+            // Checked against the local tape, but reported as the endpoint.
+            let url = tape.url();
+            let mut result = check(target.cmd.clone(), &url, ctx).await;
+            result.rpc = rpc.to_string();
+            result.error = result.error.map(|e| e.replace(&url, rpc));
+            result
+        }
+        None => check(target.cmd.clone(), rpc, ctx).await,
+    };
+
+    if let Some(tape) = tape {
+        let finished = tokio::task::spawn_blocking(|| tape.finish()).await;
+        if let Ok(Err(e)) = finished {
+            result.error = Some(format!("Cannot write recording: {}", e));
+        }
+    }
+
+    // A replayed check never contacts the endpoint, so neither does TLS.
+    if options.replay.is_none() {
+        let (host, warn_days) = (rpc.to_string(), target.tls_warn_days);
+        let (proxy, trust) = (ctx.proxy.proxy_for(rpc), ctx.trust.clone());
+        result.tls = tokio::task::spawn_blocking(move || {
+            tls::inspect(&host, warn_days, proxy.as_ref(), &trust)
+        })
+        .await
+        .unwrap_or_default();
+    }
+
+    result.labels = target.labels.clone();
+
+    redactor.apply(&mut result);
+
+    result
+}
 
-        _ => {
-            eprintln!(
-                "Unsupported combination: protocol={} method={}",
-                args.protocol, args.method
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let options = &args.options;
+
+    let run = match &args.command {
+        Cli::Tendermint(check) => Run::Endpoints(check.command(), &check.endpoints),
+        Cli::Ethereum(check) => Run::Endpoints(check.command(), &check.endpoints),
+        Cli::Bitcoin(check) => Run::Endpoints(check.command(), &check.endpoints),
+        Cli::Engine(check) => Run::Endpoints(Ok(check.command()), &check.endpoints),
+        Cli::Auto(check) => Run::Endpoints(check.command(), &check.endpoints),
+        Cli::Profile(profiles) => Run::Profiles(profiles),
+        Cli::Schema => {
+            let schema = schemars::schema_for!(types::Report);
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+            return;
+        }
+        Cli::Mock {
+            protocol,
+            scenario,
+            listen,
+        } => {
+            serve_mock(*protocol, scenario, listen);
+            return;
+        }
+        Cli::Completions { shell } => {
+            clap_complete::generate(
+                *shell,
+                &mut Args::command(),
+                "rpc-checker",
+                &mut io::stdout(),
             );
+            return;
+        }
+        Cli::Man => {
+            if let Err(e) = clap_mangen::Man::new(Args::command()).render(&mut io::stdout()) {
+                eprintln!("Cannot write man page: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    };
+
+    let redactor = match Redactor::new(&options.redact_patterns) {
+        Ok(_) if options.no_redact => Redactor::disabled(),
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let jwt_secret = match &run {
+        Run::Endpoints(_, endpoints) => endpoints.jwt_secret.as_deref(),
+        Run::Profiles(profiles) => profiles.jwt_secret.as_deref(),
+    };
+    let auth = match Auth::new(&options.headers, jwt_secret) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = ClientConfig {
+        pool_size: options.pool_size,
+        keep_alive: (options.keep_alive > 0).then(|| Duration::from_secs(options.keep_alive)),
+        http2: options.http2,
+        ..ClientConfig::default()
+    };
+    if let Some(user_agent) = &options.user_agent {
+        config.user_agent = user_agent.clone();
+    }
+    if let Some(proxy) = &options.proxy {
+        config.proxy = match Proxy::parse(proxy) {
+            Ok(p) => Route::Via(p),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+    } else if options.no_proxy {
+        config.proxy = Route::Direct;
+    } else if let Err(e) = Route::check_env() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    config.trust = Trust {
+        ca_bundles: options.ca_certs.clone(),
+        client_identity: options.client_cert.clone().zip(options.client_key.clone()),
+        pins: options.pins.clone(),
+        insecure: options.insecure,
+    };
+    if options.insecure {
+        eprintln!("Warning: --insecure skips TLS certificate verification");
+    }
+
+    let ctx = match Context::new(&config, auth) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let targets = match run {
+        Run::Endpoints(Ok(cmd), endpoints) => Ok(endpoint_targets(cmd, endpoints, options, &ctx)),
+        Run::Endpoints(Err(e), _) => usage_error(e),
+        Run::Profiles(profiles) => profile_targets(profiles, options, &config, &ctx),
+    };
+    let targets = match targets {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{}", e);
             std::process::exit(1);
         }
     };
 
-    let result = check(cmd, &args.rpc);
+    for target in &targets {
+        if matches!(target.cmd, Command::Engine(_)) && target.ctx.auth.jwt_secret.is_none() {
+            eprintln!("The engine API requires --jwt-secret");
+            std::process::exit(1);
+        }
+
+        let http = target.rpc.starts_with("http://") || target.rpc.starts_with("https://");
+        if (options.record.is_some() || options.replay.is_some()) && !http {
+            eprintln!("--record and --replay support http:// and https:// endpoints only");
+            std::process::exit(1);
+        }
+    }
 
-    println!("{}", serde_json::to_string_pretty(&result).unwrap());
+    let format = options.output;
+    let mut results = Vec::new();
+
+    // Results come back in `--rpc` order while up to `--concurrency`
+    // endpoints are being checked.
+    let mut checks = stream::iter(&targets)
+        .map(|target| check_target(options, target, &redactor))
+        .buffered(options.concurrency);
+
+    while let Some(result) = checks.next().await {
+        if format == Format::Ndjson {
+            println!("{}", output::render(format, std::slice::from_ref(&result)));
+        } else {
+            results.push(result);
+        }
+    }
+
+    if format != Format::Ndjson {
+        println!("{}", output::render(format, &results));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_flag_runs_profiles() {
+        for argv in [
+            &[
+                "rpc-checker",
+                "--profile",
+                "mainnet-eth",
+                "onion-btc",
+                "--output",
+                "table",
+            ][..],
+            &[
+                "rpc-checker",
+                "profile",
+                "mainnet-eth",
+                "onion-btc",
+                "--output",
+                "table",
+            ][..],
+        ] {
+            let args = Args::try_parse_from(argv).unwrap();
+            match args.command {
+                Cli::Profile(profile) => assert_eq!(profile.names, ["mainnet-eth", "onion-btc"]),
+                other => panic!("{:?}", other),
+            }
+            assert!(matches!(args.options.output, Format::Table));
+        }
+    }
 }
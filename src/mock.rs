@@ -0,0 +1,683 @@
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tokio_tungstenite::tungstenite::{self, Message, WebSocket};
+
+use crate::types::Protocol;
+
+/// How often websocket threads look for new blocks and for shutdown.
+const WS_TICK: Duration = Duration::from_millis(20);
+
+/// Scenarios shipped with the binary, selectable by name.
+const BUILTIN_SCENARIOS: &[(&str, &str)] = &[
+    ("healthy", include_str!("../scenarios/healthy.json")),
+    ("syncing", include_str!("../scenarios/syncing.json")),
+    ("stalled", include_str!("../scenarios/stalled.json")),
+    ("erroring", include_str!("../scenarios/erroring.json")),
+    ("slow", include_str!("../scenarios/slow.json")),
+    ("malformed", include_str!("../scenarios/malformed.json")),
+];
+
+/// How the mock node behaves. Every field but `height` is optional in
+/// scenario files.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// Latest block height when the server starts.
+    pub height: u64,
+    /// Milliseconds per new block; without it the height never moves.
+    #[serde(default)]
+    pub block_time_ms: Option<u64>,
+    /// Earliest block still served; older blocks are pruned.
+    #[serde(default)]
+    pub earliest_block: u64,
+    /// Earliest block whose state is still available. Defaults to
+    /// `earliest_block`, i.e. an archive node.
+    #[serde(default)]
+    pub earliest_state: Option<u64>,
+    #[serde(default)]
+    pub syncing: bool,
+    /// Delay before every response.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Largest `eth_getLogs` range accepted, if limited.
+    #[serde(default)]
+    pub logs_range_limit: Option<u64>,
+    /// Answer every request with this error.
+    #[serde(default)]
+    pub error: Option<ScenarioError>,
+    /// Answer every request with a body that is not JSON.
+    #[serde(default)]
+    pub malformed: bool,
+    /// Refuse JSON-RPC batches with a single `-32600` error.
+    #[serde(default)]
+    pub reject_batches: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioError {
+    /// HTTP status sent with the error.
+    pub status: u16,
+    pub code: i64,
+    pub message: String,
+}
+
+impl Scenario {
+    /// Loads a built-in scenario by name, or a scenario file by path.
+    pub fn load(name_or_path: &str) -> Result<Scenario, String> {
+        let contents = match BUILTIN_SCENARIOS.iter().find(|(n, _)| *n == name_or_path) {
+            Some((_, contents)) => contents.to_string(),
+            None => fs::read_to_string(name_or_path).map_err(|e| {
+                format!(
+                    "{}: {} (built-in scenarios: {})",
+                    name_or_path,
+                    e,
+                    BUILTIN_SCENARIOS
+                        .iter()
+                        .map(|(n, _)| *n)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?,
+        };
+
+        serde_json::from_str(&contents).map_err(|e| format!("{}: {}", name_or_path, e))
+    }
+}
+
+/// An HTTP server emulating a Tendermint, Ethereum (or engine API) or Bitcoin
+/// node. Tendermint and Ethereum nodes also accept websocket connections,
+/// with new-block subscriptions, on the next port. It stops when dropped.
+pub struct MockServer {
+    server: Arc<Server>,
+    addr: SocketAddr,
+    handle: Option<JoinHandle<()>>,
+    ws_addr: Option<SocketAddr>,
+    ws_handle: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    /// Starts serving on `addr`; use port 0 to pick free ports.
+    pub fn start(protocol: Protocol, scenario: Scenario, addr: &str) -> io::Result<MockServer> {
+        // A free port asked for the HTTP side means a free one for websockets
+        // too, not whatever follows the port picked.
+        let any_port = addr.ends_with(":0");
+        let server = Arc::new(Server::http(addr).map_err(io::Error::other)?);
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("not listening on an IP address"))?;
+
+        let node = Arc::new(Node {
+            protocol,
+            scenario,
+            started: Instant::now(),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let (server, node) = (server.clone(), node.clone());
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    node.handle(request);
+                }
+            })
+        };
+
+        let (ws_addr, ws_handle) = match protocol {
+            Protocol::Tendermint | Protocol::Ethereum => {
+                let port = if any_port { 0 } else { addr.port() + 1 };
+                let listener = TcpListener::bind(SocketAddr::new(addr.ip(), port))?;
+                listener.set_nonblocking(true)?;
+                let ws_addr = listener.local_addr()?;
+
+                let stop = stop.clone();
+                let handle = thread::spawn(move || accept_websockets(listener, node, stop));
+                (Some(ws_addr), Some(handle))
+            }
+            Protocol::Bitcoin | Protocol::Engine => (None, None),
+        };
+
+        Ok(MockServer {
+            server,
+            addr,
+            handle: Some(handle),
+            ws_addr,
+            ws_handle,
+            stop,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The websocket endpoint, for protocols with subscriptions.
+    pub fn ws_url(&self) -> Option<String> {
+        self.ws_addr.map(|addr| format!("ws://{}", addr))
+    }
+
+    /// Serves until the process is stopped.
+    pub fn wait(mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        self.stop.store(true, Ordering::Relaxed);
+        for handle in [self.handle.take(), self.ws_handle.take()]
+            .into_iter()
+            .flatten()
+        {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn accept_websockets(listener: TcpListener, node: Arc<Node>, stop: Arc<AtomicBool>) {
+    let mut connections = Vec::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let (node, stop) = (node.clone(), stop.clone());
+                connections.push(thread::spawn(move || node.serve_websocket(stream, &stop)));
+            }
+            Err(_) => thread::sleep(WS_TICK),
+        }
+    }
+
+    for connection in connections {
+        let _ = connection.join();
+    }
+}
+
+/// A new-block subscription on a websocket connection.
+struct Subscription {
+    /// The subscribing request's id, which Tendermint repeats in events.
+    id: Value,
+    /// Last height an event was sent for.
+    sent: u64,
+}
+
+struct Node {
+    protocol: Protocol,
+    scenario: Scenario,
+    started: Instant,
+}
+
+/// What to send back: an HTTP status and a JSON or raw body.
+struct Reply {
+    status: u16,
+    body: String,
+    json: bool,
+}
+
+impl Reply {
+    fn json(status: u16, body: Value) -> Reply {
+        Reply {
+            status,
+            body: body.to_string(),
+            json: true,
+        }
+    }
+
+    fn text(status: u16, body: &str) -> Reply {
+        Reply {
+            status,
+            body: body.to_string(),
+            json: false,
+        }
+    }
+}
+
+impl Node {
+    fn handle(&self, mut request: Request) {
+        let mut body = String::new();
+        let _ = request.as_reader().read_to_string(&mut body);
+
+        thread::sleep(Duration::from_millis(self.scenario.delay_ms));
+
+        let reply = if self.scenario.malformed {
+            Reply::text(200, "<html>502 Bad Gateway</html>")
+        } else {
+            match self.protocol {
+                Protocol::Tendermint => self.tendermint(request.method(), request.url()),
+                Protocol::Ethereum | Protocol::Engine => {
+                    self.json_rpc(&body, "2.0", |m, p| self.ethereum(m, p))
+                }
+                Protocol::Bitcoin => self.json_rpc(&body, "1.0", |m, p| self.bitcoin(m, p)),
+            }
+        };
+
+        let content_type = if reply.json {
+            "application/json"
+        } else {
+            "text/html"
+        };
+        let response = Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(Header::from_bytes("Content-Type", content_type).unwrap());
+
+        let _ = request.respond(response);
+    }
+
+    /// Answers JSON-RPC calls on a websocket connection and, once subscribed,
+    /// sends an event for every new block.
+    fn serve_websocket(&self, stream: TcpStream, stop: &AtomicBool) {
+        if stream.set_nonblocking(false).is_err() {
+            return;
+        }
+        let Ok(mut socket) = tungstenite::accept(stream) else {
+            return;
+        };
+        // Reads time out so that new blocks and shutdown are noticed.
+        if socket.get_ref().set_read_timeout(Some(WS_TICK)).is_err() {
+            return;
+        }
+
+        let mut subscription = None;
+
+        while !stop.load(Ordering::Relaxed) {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    let reply = self.ws_call(text.as_str(), &mut subscription);
+                    if socket.send(Message::text(reply)).is_err() {
+                        return;
+                    }
+                }
+                Ok(Message::Close(_)) => return,
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(_) => return,
+            }
+
+            if let Some(sub) = &mut subscription {
+                if !self.send_events(&mut socket, sub) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn ws_call(&self, text: &str, subscription: &mut Option<Subscription>) -> String {
+        thread::sleep(Duration::from_millis(self.scenario.delay_ms));
+
+        if self.scenario.malformed {
+            return "<html>502 Bad Gateway</html>".to_string();
+        }
+
+        let Ok(req) = serde_json::from_str::<Value>(text) else {
+            return json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": "parse error"}}).to_string();
+        };
+        let id = req["id"].clone();
+        let method = req["method"].as_str().unwrap_or_default();
+
+        if self.scenario.error.is_none() && matches!(method, "eth_subscribe" | "subscribe") {
+            *subscription = Some(Subscription {
+                id: id.clone(),
+                sent: self.height(),
+            });
+            let result = match self.protocol {
+                Protocol::Tendermint => json!({}),
+                _ => json!("0x1"),
+            };
+            return json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string();
+        }
+
+        match self.protocol {
+            // Tendermint takes the HTTP routes as methods, with the query
+            // parameters as named params.
+            Protocol::Tendermint => {
+                let query: Vec<String> = req["params"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(k, v)| format!("{}={}", k, v.as_str().unwrap_or_default()))
+                    .collect();
+                let reply =
+                    self.tendermint(&Method::Get, &format!("/{}?{}", method, query.join("&")));
+
+                match serde_json::from_str::<Value>(&reply.body) {
+                    Ok(mut v) if reply.json => {
+                        v["id"] = id;
+                        v.to_string()
+                    }
+                    _ => json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32601, "message": "Method not found"}}).to_string(),
+                }
+            }
+            _ => self
+                .json_rpc_call(&req, "2.0", |m, p| self.ethereum(m, p))
+                .1
+                .to_string(),
+        }
+    }
+
+    /// Sends events for the blocks produced since the last one sent, returning
+    /// false once the connection is gone.
+    fn send_events(&self, socket: &mut WebSocket<TcpStream>, sub: &mut Subscription) -> bool {
+        let height = self.height();
+
+        while sub.sent < height {
+            sub.sent += 1;
+            let n = sub.sent;
+
+            let event = match self.protocol {
+                Protocol::Tendermint => json!({
+                    "jsonrpc": "2.0",
+                    "id": sub.id,
+                    "result": {
+                        "query": "tm.event='NewBlock'",
+                        "data": {
+                            "type": "tendermint/event/NewBlock",
+                            "value": {"block": {"header": {"height": n.to_string()}}},
+                        },
+                    },
+                }),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": {
+                        "subscription": "0x1",
+                        "result": {
+                            "number": hex(n),
+                            "hash": format!("0x{:064x}", n),
+                            "parentHash": format!("0x{:064x}", n - 1),
+                            "timestamp": hex(1_700_000_000 + n * 12),
+                        },
+                    },
+                }),
+            };
+
+            if socket.send(Message::text(event.to_string())).is_err() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Latest height, advancing with `block_time_ms` since the server started.
+    fn height(&self) -> u64 {
+        let advanced = match self.scenario.block_time_ms {
+            Some(ms) if ms > 0 => self.started.elapsed().as_millis() as u64 / ms,
+            _ => 0,
+        };
+
+        self.scenario.height + advanced
+    }
+
+    /// Decodes a JSON-RPC request or batch and dispatches each call to
+    /// `answer`, which returns the result or a `(code, message)` error.
+    fn json_rpc(
+        &self,
+        body: &str,
+        version: &str,
+        answer: impl Fn(&str, &Value) -> Result<Value, (i64, String)>,
+    ) -> Reply {
+        let Ok(req) = serde_json::from_str::<Value>(body) else {
+            return Reply::json(
+                400,
+                json!({"jsonrpc": version, "id": null, "error": {"code": -32700, "message": "parse error"}}),
+            );
+        };
+
+        match req {
+            Value::Array(_) if self.scenario.reject_batches => Reply::json(
+                200,
+                json!({"jsonrpc": version, "id": null, "error": {"code": -32600, "message": "batch requests are not supported"}}),
+            ),
+            Value::Array(calls) => {
+                let replies: Vec<Value> = calls
+                    .iter()
+                    .map(|call| self.json_rpc_call(call, version, &answer).1)
+                    .collect();
+                Reply::json(200, Value::Array(replies))
+            }
+            call => {
+                let (status, reply) = self.json_rpc_call(&call, version, &answer);
+                Reply::json(status, reply)
+            }
+        }
+    }
+
+    fn json_rpc_call(
+        &self,
+        req: &Value,
+        version: &str,
+        answer: impl Fn(&str, &Value) -> Result<Value, (i64, String)>,
+    ) -> (u16, Value) {
+        let id = req["id"].clone();
+
+        if let Some(e) = &self.scenario.error {
+            return (
+                e.status,
+                json!({"jsonrpc": version, "id": id, "result": null, "error": {"code": e.code, "message": e.message}}),
+            );
+        }
+
+        let method = req["method"].as_str().unwrap_or_default();
+
+        match answer(method, &req["params"]) {
+            Ok(result) => (200, json!({"jsonrpc": version, "id": id, "result": result})),
+            Err((code, message)) => {
+                // Bitcoin Core reports unknown methods with 404, other errors with 500.
+                let status = match (self.protocol, code) {
+                    (Protocol::Bitcoin, -32601) => 404,
+                    (Protocol::Bitcoin, _) => 500,
+                    _ => 200,
+                };
+                (
+                    status,
+                    json!({"jsonrpc": version, "id": id, "result": null, "error": {"code": code, "message": message}}),
+                )
+            }
+        }
+    }
+
+    fn ethereum(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let height = self.height();
+        let earliest = self.scenario.earliest_block;
+        let earliest_state = self.scenario.earliest_state.unwrap_or(earliest);
+
+        let block_param = |v: &Value| match v.as_str() {
+            Some("latest") | Some("pending") | Some("safe") | Some("finalized") | None => {
+                Some(height)
+            }
+            Some("earliest") => Some(earliest),
+            Some(s) => u64::from_str_radix(s.trim_start_matches("0x"), 16).ok(),
+        };
+
+        match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "net_version" => Ok(json!("1")),
+            "web3_clientVersion" => Ok(json!("rpc-checker-mock/v0.1.0")),
+            "eth_blockNumber" => Ok(json!(hex(height))),
+            "eth_syncing" => Ok(if self.scenario.syncing {
+                json!({
+                    "startingBlock": "0x0",
+                    "currentBlock": hex(height),
+                    "highestBlock": hex(height + 100),
+                })
+            } else {
+                json!(false)
+            }),
+            // Like real nodes that expire history, genesis is always kept.
+            "eth_getBlockByNumber" => match block_param(&params[0]) {
+                Some(n) if (n >= earliest || n == 0) && n <= height => Ok(json!({
+                    "number": hex(n),
+                    "hash": format!("0x{:064x}", n),
+                    "parentHash": format!("0x{:064x}", n.saturating_sub(1)),
+                    "timestamp": hex(1_700_000_000 + n * 12),
+                    "transactions": [],
+                })),
+                _ => Ok(Value::Null),
+            },
+            "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" => {
+                match block_param(&params[1]) {
+                    Some(n) if n > height => Err((-32000, "header not found".to_string())),
+                    Some(n) if n < earliest_state => {
+                        Err((-32000, format!("missing trie node {:064x} (path )", n)))
+                    }
+                    _ => Ok(json!("0x0")),
+                }
+            }
+            "eth_gasPrice" | "eth_maxPriorityFeePerGas" => Ok(json!("0x3b9aca00")),
+            "eth_accounts" => Ok(json!([])),
+            "eth_getLogs" => {
+                let from = block_param(&params[0]["fromBlock"]).unwrap_or(height);
+                let to = block_param(&params[0]["toBlock"]).unwrap_or(height);
+                let span = to.saturating_sub(from) + 1;
+
+                match self.scenario.logs_range_limit {
+                    Some(limit) if span > limit => {
+                        Err((-32602, format!("query exceeds max block range {}", limit)))
+                    }
+                    _ => Ok(json!([])),
+                }
+            }
+            "engine_exchangeCapabilities" => Ok(params[0].clone()),
+            _ => Err((
+                -32601,
+                format!("the method {} does not exist/is not available", method),
+            )),
+        }
+    }
+
+    fn bitcoin(&self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let height = self.height();
+        let earliest = self.scenario.earliest_block;
+
+        match method {
+            "getblockchaininfo" => Ok(json!({
+                "chain": "main",
+                "blocks": height,
+                "headers": if self.scenario.syncing { height + 100 } else { height },
+                "initialblockdownload": self.scenario.syncing,
+                "pruned": earliest > 0,
+            })),
+            "getnetworkinfo" => Ok(json!({
+                "version": 270000,
+                "subversion": "/Satoshi:27.0.0/",
+                "connections": 8,
+            })),
+            "getblockcount" => Ok(json!(height)),
+            "getblockhash" => match params[0].as_u64() {
+                Some(n) if n <= height => Ok(json!(format!("{:064x}", n))),
+                _ => Err((-8, "Block height out of range".to_string())),
+            },
+            // Hashes encode their height, see getblockhash.
+            "getblockheader" => match params[0]
+                .as_str()
+                .and_then(|h| u64::from_str_radix(h, 16).ok())
+            {
+                Some(n) if n <= height => Ok(json!({"hash": params[0], "height": n})),
+                _ => Err((-5, "Block not found".to_string())),
+            },
+            "getblockstats" => match params[0].as_u64() {
+                Some(n) if n > height => {
+                    Err((-8, "Target block height after current tip".to_string()))
+                }
+                Some(n) if n < earliest => {
+                    Err((-1, "Block not available (pruned data)".to_string()))
+                }
+                Some(n) => Ok(json!({"height": n})),
+                None => Err((-8, "Invalid height".to_string())),
+            },
+            _ => Err((-32601, "Method not found".to_string())),
+        }
+    }
+
+    fn tendermint(&self, method: &Method, url: &str) -> Reply {
+        if let Some(e) = &self.scenario.error {
+            return Reply::json(
+                e.status,
+                json!({"jsonrpc": "2.0", "id": -1, "error": {"code": e.code, "message": e.message}}),
+            );
+        }
+
+        if *method != Method::Get {
+            return Reply::text(405, "Method Not Allowed");
+        }
+
+        let (route, query) = url.split_once('?').unwrap_or((url, ""));
+        let param = |name: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.trim_matches('"').to_string())
+        };
+
+        let height = self.height();
+        // Tendermint counts from block 1.
+        let earliest = self.scenario.earliest_block.max(1);
+
+        let result = match route.trim_end_matches('/') {
+            "" => {
+                return Reply::text(
+                    200,
+                    "<html><body><br>Available endpoints:<br><a href=\"//localhost/block\">//localhost/block?height=_</a><br><a href=\"//localhost/health\">//localhost/health</a><br><a href=\"//localhost/status\">//localhost/status</a></body></html>",
+                );
+            }
+            "/health" => Ok(json!({})),
+            "/status" => Ok(json!({
+                "node_info": {"network": "mock-1", "version": "0.38.0"},
+                "sync_info": {
+                    "latest_block_height": height.to_string(),
+                    "earliest_block_height": earliest.to_string(),
+                    "catching_up": self.scenario.syncing,
+                },
+            })),
+            "/block" => {
+                let n = match param("height") {
+                    Some(h) => h.parse::<u64>().unwrap_or(0),
+                    None => height,
+                };
+
+                if n > height {
+                    Err(format!(
+                        "height {} must be less than or equal to the current blockchain height {}",
+                        n, height
+                    ))
+                } else if n < earliest {
+                    Err(format!(
+                        "height {} is not available, lowest height is {}",
+                        n, earliest
+                    ))
+                } else {
+                    Ok(json!({"block": {"header": {"height": n.to_string()}}}))
+                }
+            }
+            _ => return Reply::text(404, "404 page not found"),
+        };
+
+        match result {
+            Ok(result) => Reply::json(200, json!({"jsonrpc": "2.0", "id": -1, "result": result})),
+            Err(data) => Reply::json(
+                500,
+                json!({"jsonrpc": "2.0", "id": -1, "error": {"code": -32603, "message": "Internal error", "data": data}}),
+            ),
+        }
+    }
+}
+
+fn hex(n: u64) -> String {
+    format!("0x{:x}", n)
+}
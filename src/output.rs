@@ -0,0 +1,541 @@
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::types::{
+    CheckResult, DeliveryQuality, NodeKind, Report, ResultData, Severity, SCHEMA_VERSION,
+};
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Pretty-printed JSON: an object for one target, an array for several.
+    Json,
+    /// One compact JSON object per line, printed as each target finishes.
+    Ndjson,
+    /// Aligned summary, coloured on a terminal.
+    Table,
+    /// One row per target.
+    Csv,
+    /// YAML document, a list for several targets.
+    Yaml,
+    /// JSON on a single line.
+    Compact,
+    /// JUnit XML, one test case per target.
+    Junit,
+    /// Test Anything Protocol, one test point per target.
+    Tap,
+}
+
+/// Renders results in `format`. NDJSON is rendered one result at a time by
+/// the caller, so here it simply joins the lines.
+pub fn render(format: Format, results: &[CheckResult]) -> String {
+    let reports: Vec<Report> = results.iter().map(Report::new).collect();
+
+    match format {
+        Format::Json => match reports.as_slice() {
+            [one] => serde_json::to_string_pretty(one).unwrap(),
+            _ => serde_json::to_string_pretty(&reports).unwrap(),
+        },
+        Format::Compact => match reports.as_slice() {
+            [one] => serde_json::to_string(one).unwrap(),
+            _ => serde_json::to_string(&reports).unwrap(),
+        },
+        Format::Ndjson => reports
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Yaml => {
+            let yaml = match reports.as_slice() {
+                [one] => serde_yaml::to_string(one).unwrap(),
+                _ => serde_yaml::to_string(&reports).unwrap(),
+            };
+            yaml.trim_end().to_string()
+        }
+        Format::Csv => render_csv(results),
+        Format::Table => render_table(results, use_color()),
+        Format::Junit => render_junit(results),
+        Format::Tap => render_tap(results),
+    }
+}
+
+/// A target passes when it was reachable, the check reported no error and
+/// its result is a good one; see [`failure`].
+fn passed(result: &CheckResult) -> bool {
+    failure(result).is_none()
+}
+
+fn failure_message(result: &CheckResult) -> String {
+    failure(result).unwrap_or_default()
+}
+
+/// Why a target fails: unreachable, an error, a result that answered but is
+/// bad (unhealthy, no events delivered, high or critical audit findings, not
+/// an archive node) or TLS warnings. With `--insecure` only an expired
+/// certificate counts, the untrusted chain having been asked for.
+fn failure(result: &CheckResult) -> Option<String> {
+    if let Some(e) = &result.error {
+        return Some(e.clone());
+    }
+    if !result.reachable {
+        return Some("unreachable".to_string());
+    }
+
+    let bad = match &result.result {
+        Some(ResultData::Health { healthy: false }) => Some("unhealthy".to_string()),
+        Some(ResultData::Archive {
+            node: Some(node @ (NodeKind::Full | NodeKind::Pruned)),
+            block,
+        }) => Some(format!(
+            "not an archive node: {} at block {}",
+            serde_json::to_value(node)
+                .unwrap()
+                .as_str()
+                .unwrap_or_default(),
+            block
+        )),
+        Some(ResultData::Delivery {
+            quality: DeliveryQuality::Failed,
+            ..
+        }) => Some("no events delivered".to_string()),
+        Some(ResultData::Audit { findings }) => {
+            let serious: Vec<&str> = findings
+                .iter()
+                .filter(|f| f.severity >= Severity::High)
+                .map(|f| f.target.as_str())
+                .collect();
+            (!serious.is_empty()).then(|| {
+                format!(
+                    "{} high or critical findings: {}",
+                    serious.len(),
+                    serious.join(", ")
+                )
+            })
+        }
+        _ => None,
+    };
+    if bad.is_some() {
+        return bad;
+    }
+
+    let tls = result.tls.as_ref()?;
+    if result.insecure {
+        return tls
+            .days_remaining
+            .filter(|days| *days < 0)
+            .map(|days| format!("certificate expired {} days ago", -days));
+    }
+    (!tls.warnings.is_empty()).then(|| tls.warnings.join("; "))
+}
+
+fn render_junit(results: &[CheckResult]) -> String {
+    let failures = results.iter().filter(|r| !passed(r)).count();
+
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        format!(
+            r#"<testsuites name="rpc-checker" tests="{}" failures="{}">"#,
+            results.len(),
+            failures
+        ),
+        format!(
+            r#"  <testsuite name="rpc-checker" tests="{}" failures="{}">"#,
+            results.len(),
+            failures
+        ),
+    ];
+
+    for r in results {
+        let (kind, summary) = describe(r);
+        let classname = if kind.is_empty() {
+            r.protocol.clone()
+        } else {
+            format!("{}.{}", r.protocol, kind)
+        };
+
+        lines.push(format!(
+            r#"    <testcase classname="{}" name="{}">"#,
+            xml_escape(&classname),
+            xml_escape(&r.rpc)
+        ));
+        if !passed(r) {
+            let message = xml_escape(&failure_message(r));
+            lines.push(format!(
+                r#"      <failure message="{}">{}</failure>"#,
+                message, message
+            ));
+        }
+        if !summary.is_empty() {
+            lines.push(format!(
+                "      <system-out>{}</system-out>",
+                xml_escape(&summary)
+            ));
+        }
+        lines.push("    </testcase>".to_string());
+    }
+
+    lines.push("  </testsuite>".to_string());
+    lines.push("</testsuites>".to_string());
+    lines.join("\n")
+}
+
+/// Escapes markup and replaces characters XML 1.0 forbids even when escaped
+/// (control characters other than tab, LF and CR, U+FFFE and U+FFFF), which
+/// node error messages occasionally carry.
+fn xml_escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => c,
+            '\0'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => '\u{fffd}',
+            _ => c,
+        })
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn render_tap(results: &[CheckResult]) -> String {
+    let mut lines = vec![
+        "TAP version 13".to_string(),
+        format!("1..{}", results.len()),
+    ];
+
+    for (i, r) in results.iter().enumerate() {
+        let status = if passed(r) { "ok" } else { "not ok" };
+        lines.push(format!("{} {} - {} {}", status, i + 1, r.protocol, r.rpc));
+
+        // YAML diagnostics block; JSON strings are valid YAML scalars.
+        if !passed(r) {
+            lines.push("  ---".to_string());
+            lines.push(format!("  message: {}", Value::String(failure_message(r))));
+            lines.push("  ...".to_string());
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn render_csv(results: &[CheckResult]) -> String {
+    let mut lines = vec!["schema_version,protocol,rpc,reachable,type,summary,error".to_string()];
+
+    for r in results {
+        let (kind, summary) = describe(r);
+        let fields = [
+            SCHEMA_VERSION.to_string(),
+            r.protocol.clone(),
+            r.rpc.clone(),
+            r.reachable.to_string(),
+            kind,
+            summary,
+            r.error.clone().unwrap_or_default(),
+        ];
+
+        lines.push(
+            fields
+                .iter()
+                .map(|f| csv_field(f))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+
+    lines.join("\n")
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render_table(results: &[CheckResult], color: bool) -> String {
+    let header = ["PROTOCOL", "RPC", "STATUS", "RESULT", "ERROR"].map(|h| h.to_string());
+
+    let rows: Vec<[String; 5]> = results
+        .iter()
+        .map(|r| {
+            let (kind, summary) = describe(r);
+            let result = if summary.is_empty() {
+                kind
+            } else {
+                format!("{} {}", kind, summary)
+            };
+
+            [
+                r.protocol.clone(),
+                r.rpc.clone(),
+                match (r.reachable, passed(r), r.insecure) {
+                    (false, _, _) => "down",
+                    (true, false, _) => "failed",
+                    (true, true, true) => "up (insecure)",
+                    (true, true, false) => "up",
+                }
+                .to_string(),
+                result,
+                failure_message(r),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+
+    // Pad before colouring so escape codes do not skew the alignment.
+    let line = |cells: &[String; 5], styles: [Option<&str>; 5]| {
+        cells
+            .iter()
+            .zip(widths)
+            .zip(styles)
+            .map(|((cell, width), style)| {
+                let padded = format!("{:<width$}", cell, width = width);
+                match style {
+                    Some(style) if color => format!("{}{}{}", style, padded, RESET),
+                    _ => padded,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![line(&header, [Some(BOLD); 5])];
+    for (row, result) in rows.iter().zip(results) {
+        let status = if passed(result) { GREEN } else { RED };
+        let error = (!passed(result)).then_some(RED);
+        lines.push(line(row, [None, None, Some(status), None, error]));
+    }
+
+    lines.join("\n")
+}
+
+/// The result type and its fields as `key=value` pairs. Nested objects are
+/// flattened with dotted keys and lists reduced to their length.
+fn describe(result: &CheckResult) -> (String, String) {
+    let Some(data) = &result.result else {
+        return (String::new(), String::new());
+    };
+
+    let value = serde_json::to_value(data).unwrap();
+    let kind = value["type"].as_str().unwrap_or_default().to_string();
+
+    let mut pairs = Vec::new();
+    if let Value::Object(fields) = &value {
+        for (key, v) in fields.iter().filter(|(k, _)| *k != "type") {
+            flatten(key, v, &mut pairs);
+        }
+    }
+
+    (kind, pairs.join(" "))
+}
+
+fn flatten(key: &str, value: &Value, pairs: &mut Vec<String>) {
+    match value {
+        Value::Null => {}
+        Value::Object(fields) => {
+            for (k, v) in fields {
+                flatten(&format!("{}.{}", key, k), v, pairs);
+            }
+        }
+        Value::Array(items) => pairs.push(format!("{}=[{}]", key, items.len())),
+        Value::String(s) => pairs.push(format!("{}={}", key, s)),
+        other => pairs.push(format!("{}={}", key, other)),
+    }
+}
+
+/// Colour only when writing to a terminal and `NO_COLOR` is not set.
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && std::io::stdout().is_terminal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Finding, TlsInfo};
+
+    fn result(rpc: &str, data: Option<ResultData>) -> CheckResult {
+        CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: data,
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        }
+    }
+
+    fn tls(days_remaining: i64, warnings: &[&str]) -> Option<TlsInfo> {
+        Some(TlsInfo {
+            version: Some("TLSv1.3".to_string()),
+            trusted: true,
+            hostname_match: true,
+            days_remaining: Some(days_remaining),
+            chain: vec![],
+            warnings: warnings.iter().map(|w| w.to_string()).collect(),
+            error: None,
+        })
+    }
+
+    fn status() -> Option<ResultData> {
+        Some(ResultData::Status {
+            latest_block: Some(100),
+            syncing: Some(false),
+        })
+    }
+
+    #[test]
+    fn failures_come_from_the_result() {
+        let down = CheckResult {
+            reachable: false,
+            error: Some("connection refused".to_string()),
+            ..result("http://a", None)
+        };
+        let unhealthy = result("http://a", Some(ResultData::Health { healthy: false }));
+        let pruned = result(
+            "http://a",
+            Some(ResultData::Archive {
+                block: 1,
+                node: Some(NodeKind::Pruned),
+            }),
+        );
+        let no_events = result(
+            "ws://a",
+            Some(ResultData::Delivery {
+                event: "newHeads".to_string(),
+                duration_secs: 60,
+                events: 0,
+                first_height: None,
+                last_height: None,
+                latest_polled: Some(100),
+                missed_heights: 0,
+                max_gap_ms: None,
+                mean_gap_ms: None,
+                reconnections: 0,
+                quality: DeliveryQuality::Failed,
+            }),
+        );
+        let audit = |severity| {
+            result(
+                "http://a",
+                Some(ResultData::Audit {
+                    findings: vec![Finding {
+                        target: "admin_nodeInfo".to_string(),
+                        severity,
+                        description: "admin namespace is exposed".to_string(),
+                    }],
+                }),
+            )
+        };
+        let expiring = CheckResult {
+            tls: tls(3, &["certificate expires in 3 days (2026-10-21)"]),
+            ..result("https://a", status())
+        };
+        let insecure = CheckResult {
+            tls: tls(200, &["certificate chain is not trusted: UnknownIssuer"]),
+            insecure: true,
+            ..result("https://a", status())
+        };
+
+        assert_eq!(failure(&down).as_deref(), Some("connection refused"));
+        assert_eq!(failure(&unhealthy).as_deref(), Some("unhealthy"));
+        assert_eq!(
+            failure(&pruned).as_deref(),
+            Some("not an archive node: pruned at block 1")
+        );
+        assert_eq!(failure(&no_events).as_deref(), Some("no events delivered"));
+        assert_eq!(
+            failure(&audit(Severity::Critical)).as_deref(),
+            Some("1 high or critical findings: admin_nodeInfo")
+        );
+        assert_eq!(failure(&audit(Severity::Low)), None);
+        assert_eq!(
+            failure(&expiring).as_deref(),
+            Some("certificate expires in 3 days (2026-10-21)")
+        );
+        assert_eq!(failure(&insecure), None);
+        assert_eq!(failure(&result("http://a", status())), None);
+    }
+
+    fn mixed() -> Vec<CheckResult> {
+        vec![
+            result("http://a", status()),
+            result("http://b", Some(ResultData::Health { healthy: false })),
+        ]
+    }
+
+    #[test]
+    fn junit() {
+        assert_eq!(
+            render(Format::Junit, &mixed()),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="rpc-checker" tests="2" failures="1">
+  <testsuite name="rpc-checker" tests="2" failures="1">
+    <testcase classname="ethereum.status" name="http://a">
+      <system-out>latest_block=100 syncing=false</system-out>
+    </testcase>
+    <testcase classname="ethereum.health" name="http://b">
+      <failure message="unhealthy">unhealthy</failure>
+      <system-out>healthy=false</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>"#
+        );
+    }
+
+    #[test]
+    fn tap() {
+        assert_eq!(
+            render(Format::Tap, &mixed()),
+            "TAP version 13\n1..2\nok 1 - ethereum http://a\nnot ok 2 - ethereum http://b\n  ---\n  message: \"unhealthy\"\n  ..."
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let mut results = mixed();
+        results[1].error = Some("bad, \"quoted\"".to_string());
+
+        assert_eq!(
+            render(Format::Csv, &results),
+            "schema_version,protocol,rpc,reachable,type,summary,error\n\
+             1,ethereum,http://a,true,status,latest_block=100 syncing=false,\n\
+             1,ethereum,http://b,true,health,healthy=false,\"bad, \"\"quoted\"\"\""
+        );
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            render_table(&mixed(), false),
+            "PROTOCOL  RPC       STATUS  RESULT                                 ERROR\n\
+             ethereum  http://a  up      status latest_block=100 syncing=false\n\
+             ethereum  http://b  failed  health healthy=false                   unhealthy"
+        );
+    }
+
+    #[test]
+    fn xml_escape_forbidden_characters() {
+        assert_eq!(
+            xml_escape("bad \u{0}\u{1b}[0m <body>\tok\n"),
+            "bad \u{fffd}\u{fffd}[0m &lt;body&gt;\tok\n"
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::env;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Largest response head accepted from an HTTP proxy's `CONNECT`.
+const MAX_CONNECT_RESPONSE: usize = 8192;
+
+/// A proxy to reach endpoints through: `http://` tunnels with `CONNECT`,
+/// `socks5://` resolves names locally and `socks5h://` leaves them to the
+/// proxy, as Tor's `.onion` addresses need.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proxy {
+    url: Url,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Http,
+    Socks5 { remote_dns: bool },
+}
+
+/// Which proxy, if any, endpoints are reached through. Loopback endpoints
+/// such as a local mock or recording are always reached directly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Route {
+    /// Whatever `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` say.
+    #[default]
+    Env,
+    /// No proxy, whatever the environment says.
+    Direct,
+    Via(Proxy),
+}
+
+impl Proxy {
+    /// Parses a proxy URL. Like curl, a bare `host:port` is an HTTP proxy.
+    pub fn parse(proxy: &str) -> Result<Proxy, String> {
+        let full = if proxy.contains("://") {
+            proxy.to_string()
+        } else {
+            format!("http://{}", proxy)
+        };
+        let url = Url::parse(&full).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?;
+
+        let kind = match url.scheme() {
+            "http" => Kind::Http,
+            "socks5" => Kind::Socks5 { remote_dns: false },
+            "socks5h" => Kind::Socks5 { remote_dns: true },
+            "https" => {
+                return Err(format!(
+                    "Unsupported proxy {}: TLS to the proxy is not supported; use http://, socks5:// or socks5h://",
+                    proxy
+                ))
+            }
+            other => {
+                return Err(format!(
+                    "Unsupported proxy scheme {}: use http://, socks5:// or socks5h://",
+                    other
+                ))
+            }
+        };
+        if url.host_str().is_none() {
+            return Err(format!("Invalid proxy {}: no host", proxy));
+        }
+
+        Ok(Proxy { url, kind })
+    }
+
+    /// The proxy URL, credentials included, as HTTP clients expect it.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Opens a TCP connection to `host:port` through the proxy.
+    pub async fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        let proxy_host = self.url.host_str().unwrap_or_default();
+        let proxy_port = self.url.port_or_known_default().unwrap_or(1080);
+        let mut stream = TcpStream::connect((unbracket(proxy_host), proxy_port)).await?;
+
+        match self.kind {
+            Kind::Http => self.http_connect(&mut stream, host, port).await?,
+            Kind::Socks5 { remote_dns } => {
+                self.socks5_connect(&mut stream, host, port, remote_dns)
+                    .await?
+            }
+        }
+
+        Ok(stream)
+    }
+
+    fn credentials(&self) -> Option<(String, String)> {
+        if self.url.username().is_empty() {
+            return None;
+        }
+        let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+
+        Some((
+            decode(self.url.username()),
+            decode(self.url.password().unwrap_or_default()),
+        ))
+    }
+
+    async fn http_connect(&self, stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+        let authority = if host.contains(':') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        };
+
+        let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+        if let Some((user, password)) = self.credentials() {
+            request.push_str(&format!(
+                "Proxy-Authorization: Basic {}\r\n",
+                STANDARD.encode(format!("{}:{}", user, password))
+            ));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // One byte at a time, so nothing past the head is taken from the tunnel.
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            if head.len() >= MAX_CONNECT_RESPONSE {
+                return Err(failure("the proxy sent an oversized response"));
+            }
+            head.push(stream.read_u8().await?);
+        }
+
+        let head = String::from_utf8_lossy(&head);
+        let status_line = head.lines().next().unwrap_or_default();
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            return Err(failure(format!(
+                "the proxy refused to connect to {}: {}",
+                authority, status_line
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn socks5_connect(
+        &self,
+        stream: &mut TcpStream,
+        host: &str,
+        port: u16,
+        remote_dns: bool,
+    ) -> io::Result<()> {
+        const NO_AUTH: u8 = 0x00;
+        const PASSWORD: u8 = 0x02;
+
+        let credentials = self.credentials();
+        let methods: &[u8] = if credentials.is_some() {
+            &[NO_AUTH, PASSWORD]
+        } else {
+            &[NO_AUTH]
+        };
+        let mut greeting = vec![5, methods.len() as u8];
+        greeting.extend(methods);
+        stream.write_all(&greeting).await?;
+
+        let mut chosen = [0u8; 2];
+        stream.read_exact(&mut chosen).await?;
+        match (chosen, &credentials) {
+            ([5, NO_AUTH], _) => {}
+            ([5, PASSWORD], Some((user, password))) => {
+                if user.len() > 255 || password.len() > 255 {
+                    return Err(failure("SOCKS credentials are limited to 255 bytes"));
+                }
+                let mut login = vec![1, user.len() as u8];
+                login.extend(user.as_bytes());
+                login.push(password.len() as u8);
+                login.extend(password.as_bytes());
+                stream.write_all(&login).await?;
+
+                let mut status = [0u8; 2];
+                stream.read_exact(&mut status).await?;
+                if status[1] != 0 {
+                    return Err(failure("the SOCKS proxy rejected the credentials"));
+                }
+            }
+            ([5, _], _) => {
+                return Err(failure(
+                    "the SOCKS proxy accepts none of the offered authentication methods",
+                ))
+            }
+            _ => return Err(failure("not a SOCKS5 proxy")),
+        }
+
+        let mut request = vec![5, 1, 0];
+        let ip = match host.parse::<IpAddr>() {
+            Ok(ip) => Some(ip),
+            Err(_) if remote_dns => None,
+            Err(_) => Some(
+                tokio::net::lookup_host((host, port))
+                    .await?
+                    .next()
+                    .ok_or_else(|| failure(format!("no address for {}", host)))?
+                    .ip(),
+            ),
+        };
+        match ip {
+            Some(IpAddr::V4(ip)) => {
+                request.push(1);
+                request.extend(ip.octets());
+            }
+            Some(IpAddr::V6(ip)) => {
+                request.push(4);
+                request.extend(ip.octets());
+            }
+            None => {
+                if host.len() > 255 {
+                    return Err(failure(format!("host name too long for SOCKS: {}", host)));
+                }
+                request.push(3);
+                request.push(host.len() as u8);
+                request.extend(host.as_bytes());
+            }
+        }
+        request.extend(port.to_be_bytes());
+        stream.write_all(&request).await?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[1] != 0 {
+            return Err(failure(format!(
+                "the SOCKS proxy could not connect to {}: {}",
+                host,
+                socks_error(reply[1])
+            )));
+        }
+
+        // Skip the bound address and port the proxy reports.
+        let bound = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => stream.read_u8().await? as usize,
+            _ => return Err(failure("malformed SOCKS reply")),
+        };
+        let mut skipped = vec![0u8; bound + 2];
+        stream.read_exact(&mut skipped).await?;
+
+        Ok(())
+    }
+}
+
+/// Shows the proxy without its credentials.
+impl fmt::Display for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}://{}",
+            self.url.scheme(),
+            self.url.host_str().unwrap_or_default()
+        )?;
+        if let Some(port) = self.url.port() {
+            write!(f, ":{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+impl Route {
+    /// The proxy to reach `target` through, if any.
+    pub fn proxy_for(&self, target: &str) -> Option<Proxy> {
+        let url = Url::parse(target).ok()?;
+        let host = unbracket(url.host_str()?);
+        if is_loopback(host) {
+            return None;
+        }
+
+        match self {
+            Route::Direct => None,
+            Route::Via(proxy) => Some(proxy.clone()),
+            Route::Env => {
+                if bypassed(
+                    host,
+                    &env_var(&["NO_PROXY", "no_proxy"]).unwrap_or_default(),
+                ) {
+                    return None;
+                }
+                let scheme_var: &[&str] = match url.scheme() {
+                    "https" | "wss" => &["HTTPS_PROXY", "https_proxy"],
+                    _ => &["HTTP_PROXY", "http_proxy"],
+                };
+                // Unusable values are caught up front by `check_env`.
+                env_var(scheme_var)
+                    .or_else(|| env_var(&["ALL_PROXY", "all_proxy"]))
+                    .and_then(|p| Proxy::parse(&p).ok())
+            }
+        }
+    }
+
+    /// Checks the proxy variables [`Route::Env`] reads, so that a value it
+    /// cannot use fails the run rather than letting traffic go out directly.
+    pub fn check_env() -> Result<(), String> {
+        let names = [
+            "HTTP_PROXY",
+            "http_proxy",
+            "HTTPS_PROXY",
+            "https_proxy",
+            "ALL_PROXY",
+            "all_proxy",
+        ];
+
+        for name in names {
+            if let Some(value) = env_var(&[name]) {
+                Proxy::parse(&value).map_err(|e| format!("{}: {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// Whether `host` matches a `NO_PROXY` list: `*`, exact hosts, and domains
+/// matching themselves and their subdomains (with or without a leading dot).
+fn bypassed(host: &str, no_proxy: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            let domain = entry.trim_start_matches('.');
+            entry == "*" || host == domain || host.ends_with(&format!(".{}", domain))
+        })
+}
+
+fn is_loopback(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+fn unbracket(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn socks_error(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+fn failure(message: impl Into<String>) -> io::Error {
+    io::Error::other(message.into())
+}
@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::context::Context;
+use crate::redact::Redactor;
+
+const REDACTED: &str = "***";
+
+/// Request headers whose values are never written to a recording.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "api-key",
+    "x-auth-token",
+];
+
+/// Headers that describe the connection rather than the request.
+const HOP_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "transfer-encoding",
+    "keep-alive",
+    "accept-encoding",
+];
+
+/// One request/response pair as stored in `NNNN.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Exchange {
+    seq: u32,
+    /// Time the upstream took to answer.
+    elapsed_ms: u64,
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    /// Path and query relative to the endpoint URL.
+    path: String,
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    content_type: Option<String>,
+    body: String,
+    /// Set when the upstream could not be reached; answered with a 502.
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Session {
+    rpc: String,
+    recorded_at: String,
+}
+
+/// The recording directory for one endpoint: `dir` plus a name derived from
+/// the redacted URL, so recording and replaying the same `--rpc` agree. A
+/// short hash of the full URL keeps endpoints that differ only in a redacted
+/// key apart.
+pub fn session_dir(dir: &Path, rpc: &str, redactor: &Redactor) -> PathBuf {
+    let redacted = redactor.redact_url(rpc);
+    let without_scheme = redacted
+        .split_once("://")
+        .map_or(redacted.as_str(), |(_, rest)| rest);
+
+    let name: String = without_scheme
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let digest = Sha256::digest(rpc.as_bytes());
+    let hash: String = digest[..4].iter().map(|b| format!("{:02x}", b)).collect();
+
+    dir.join(format!("{}-{}", name.trim_matches('_'), hash))
+}
+
+/// The `NNNN.json` exchange files in `dir`, in recording order.
+fn exchange_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", dir.display(), e)))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().and_then(|n| n.to_str()) != Some("session.json"))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// A local HTTP server standing in for an endpoint while a check runs: it
+/// either forwards to the endpoint and records, or replays a recording.
+pub struct Tape {
+    server: Arc<Server>,
+    addr: SocketAddr,
+    handle: Option<JoinHandle<()>>,
+    /// First failure to write a recording, reported when the tape stops.
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Tape {
+    /// Forwards to `rpc` with the proxy and TLS settings in `ctx`, writing
+    /// each exchange to `dir`. Values of the headers in `ctx.auth` are
+    /// redacted along with the usual secret headers. Exchanges left in `dir`
+    /// by an earlier recording are removed first.
+    pub fn record(rpc: &str, dir: &Path, ctx: &Context, redactor: &Redactor) -> io::Result<Tape> {
+        fs::create_dir_all(dir)?;
+        for path in exchange_files(dir)? {
+            fs::remove_file(&path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        }
+
+        let session = Session {
+            rpc: redactor.redact_url(rpc),
+            recorded_at: time::OffsetDateTime::now_utc()
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap_or_default(),
+        };
+        write_json(&dir.join("session.json"), &session)?;
+
+        let client = recording_client(rpc, ctx)?;
+        let upstream = rpc.to_string();
+        let redactor = redactor.clone();
+        let dir = dir.to_path_buf();
+        let mut secret: Vec<String> = SECRET_HEADERS.iter().map(|h| h.to_string()).collect();
+        secret.extend(
+            ctx.auth
+                .headers
+                .iter()
+                .map(|(name, _)| name.to_ascii_lowercase()),
+        );
+        let mut seq = 0;
+
+        Tape::serve(move |request, error| {
+            seq += 1;
+            let exchange = forward(&client, &upstream, request, &secret, &redactor, seq);
+            if let Err(e) = write_json(&dir.join(format!("{:04}.json", seq)), &exchange) {
+                error.get_or_insert_with(|| format!("{}: {}", dir.display(), e));
+            }
+            exchange.response
+        })
+    }
+
+    /// Answers requests from the recording in `dir`. Each recorded exchange
+    /// is used once, matched on method, path and body, and answered after the
+    /// recorded delay.
+    pub fn replay(dir: &Path) -> io::Result<Tape> {
+        let mut exchanges: Vec<Exchange> = Vec::new();
+
+        for path in exchange_files(dir)? {
+            let contents = fs::read_to_string(&path)?;
+            let exchange = serde_json::from_str(&contents).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })?;
+            exchanges.push(exchange);
+        }
+
+        let mut remaining: Vec<Option<Exchange>> = exchanges.into_iter().map(Some).collect();
+
+        Tape::serve(move |request, _| {
+            let found = remaining.iter_mut().find(|slot| {
+                slot.as_ref().is_some_and(|e| {
+                    e.request.method == request.method
+                        && e.request.path == request.path
+                        && e.request.body == request.body
+                })
+            });
+
+            match found.and_then(|slot| slot.take()) {
+                Some(exchange) => {
+                    thread::sleep(Duration::from_millis(exchange.elapsed_ms));
+                    exchange.response
+                }
+                None => RecordedResponse {
+                    status: 599,
+                    content_type: Some("text/plain".to_string()),
+                    body: format!(
+                        "No recorded response for {} {}",
+                        request.method, request.path
+                    ),
+                    error: None,
+                },
+            }
+        })
+    }
+
+    fn serve(
+        mut answer: impl FnMut(&RecordedRequest, &mut Option<String>) -> RecordedResponse
+            + Send
+            + 'static,
+    ) -> io::Result<Tape> {
+        let server = Arc::new(Server::http("127.0.0.1:0").map_err(io::Error::other)?);
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| io::Error::other("not listening on an IP address"))?;
+        let error = Arc::new(Mutex::new(None));
+
+        let handle = {
+            let server = server.clone();
+            let error = error.clone();
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let recorded = read_request(&mut request);
+                    let response = answer(&recorded, &mut error.lock().unwrap());
+                    respond(request, response);
+                }
+            })
+        };
+
+        Ok(Tape {
+            server,
+            addr,
+            handle: Some(handle),
+            error,
+        })
+    }
+
+    /// URL to run the check against in place of the endpoint.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Stops serving, returning the first error met while recording.
+    pub fn finish(mut self) -> Result<(), String> {
+        self.stop();
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Tape {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Builds the blocking client that forwards to `rpc`. It is built and later
+/// dropped off the async runtime, which the blocking client does not allow.
+fn recording_client(rpc: &str, ctx: &Context) -> io::Result<reqwest::blocking::Client> {
+    let proxy = ctx.proxy.proxy_for(rpc).map(|p| p.url().clone());
+    let tls = (*ctx.tls).clone();
+
+    let built = thread::spawn(move || {
+        let builder = reqwest::blocking::Client::builder().use_preconfigured_tls(tls);
+        let builder = match proxy {
+            Some(url) => builder.proxy(reqwest::Proxy::all(url)?),
+            None => builder.no_proxy(),
+        };
+        builder.build()
+    })
+    .join()
+    .map_err(|_| io::Error::other("Cannot build the recording client"))?;
+
+    built.map_err(|e| io::Error::other(format!("Cannot build the recording client: {}", e)))
+}
+
+fn read_request(request: &mut Request) -> RecordedRequest {
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+
+    let headers = request
+        .headers()
+        .iter()
+        .map(|h| {
+            (
+                h.field.as_str().as_str().to_ascii_lowercase(),
+                h.value.as_str().to_string(),
+            )
+        })
+        .filter(|(name, _)| !HOP_HEADERS.contains(&name.as_str()))
+        .collect();
+
+    RecordedRequest {
+        method: request.method().as_str().to_string(),
+        path: request.url().to_string(),
+        headers,
+        body,
+    }
+}
+
+/// Sends the request on to the endpoint, timing the answer. Secret header
+/// values are sent upstream but redacted in the returned exchange, as are
+/// credentials in the endpoint URL that errors quote.
+fn forward(
+    client: &reqwest::blocking::Client,
+    upstream: &str,
+    request: &RecordedRequest,
+    secret: &[String],
+    redactor: &Redactor,
+    seq: u32,
+) -> Exchange {
+    // Mirrors how checks build URLs: the endpoint itself, or `{rpc}/{route}`.
+    let url = if request.path == "/" {
+        upstream.to_string()
+    } else {
+        format!("{}{}", upstream.trim_end_matches('/'), request.path)
+    };
+
+    let method = reqwest::Method::from_bytes(request.method.as_bytes()).unwrap_or_default();
+    let mut builder = client.request(method, url).body(request.body.clone());
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+
+    let started = Instant::now();
+    let response = match builder.send() {
+        Ok(r) => {
+            let status = r.status().as_u16();
+            let content_type = r
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string());
+            let mut body = String::new();
+            let error = r
+                .text()
+                .map(|t| body = t)
+                .err()
+                .map(|e| redactor.redact_text(&e.to_string()));
+
+            RecordedResponse {
+                status,
+                content_type,
+                body,
+                error,
+            }
+        }
+        Err(e) => {
+            let error = redactor.redact_text(&e.to_string());
+
+            RecordedResponse {
+                status: 502,
+                content_type: Some("text/plain".to_string()),
+                body: error.clone(),
+                error: Some(error),
+            }
+        }
+    };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    let headers = request
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            if secret.contains(name) {
+                (name.clone(), REDACTED.to_string())
+            } else {
+                (name.clone(), value.clone())
+            }
+        })
+        .collect();
+
+    Exchange {
+        seq,
+        elapsed_ms,
+        request: RecordedRequest {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            headers,
+            body: request.body.clone(),
+        },
+        response,
+    }
+}
+
+fn respond(request: Request, recorded: RecordedResponse) {
+    let mut response = Response::from_string(recorded.body).with_status_code(recorded.status);
+    if let Some(content_type) = recorded.content_type {
+        if let Ok(header) = Header::from_bytes("Content-Type", content_type) {
+            response = response.with_header(header);
+        }
+    }
+
+    let _ = request.respond(response);
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> io::Result<()> {
+    fs::write(
+        path,
+        serde_json::to_string_pretty(value).map_err(io::Error::other)?,
+    )
+}
@@ -1,263 +1,307 @@
+use crate::audit::{check_bitcoin_audit, check_ethereum_audit, check_tendermint_audit};
+use crate::capabilities::check_ethereum_capabilities;
+use crate::context::{self, Context};
+use crate::detect;
+use crate::engine::check_engine_status;
+use crate::jsonrpc::{self, CallError};
+use crate::logs::check_ethereum_logs;
+use crate::subscription::{
+    check_ethereum_delivery, check_ethereum_subscription, check_tendermint_delivery,
+    check_tendermint_subscription,
+};
+use crate::transport::Transport;
 use crate::types::{
-    BitcoinMethod, CheckResult, Command, EthereumMethod, ResultData, TendermintMethod,
+    BitcoinMethod, CheckResult, Command, EngineMethod, EthereumMethod, NodeKind, ResultData,
+    TendermintMethod,
 };
 
-pub fn check(cmd: Command, rpc: &str) -> CheckResult {
+/// Block used for archive detection when no height is given. Any block older
+/// than the ~128 blocks of state kept by full nodes works; block 1 exists on
+/// every chain.
+const DEFAULT_ARCHIVE_BLOCK: u64 = 1;
+
+pub(crate) const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Error fragments clients return when the state for a block has been pruned.
+const MISSING_STATE_ERRORS: &[&str] = &[
+    "missing trie node",
+    "state not available",
+    "state is not available",
+    "historical state",
+    "no state available",
+    "state unavailable",
+    "world state",
+    "state pruned",
+    "state has been pruned",
+];
+
+/// Error fragments clients return when the block itself is unknown. Checked
+/// before the state errors, since "block pruned" is about the block.
+const MISSING_BLOCK_ERRORS: &[&str] = &[
+    "header not found",
+    "unknown block",
+    "block not found",
+    "block pruned",
+    "pruned block",
+    "block has been pruned",
+];
+
+/// Outcome of asking an endpoint for a single historical block.
+enum Probe {
+    Available,
+    /// The block is not served; `lowest` carries the earliest height if the
+    /// node told us (e.g. Tendermint "lowest height is Y").
+    Unavailable {
+        lowest: Option<u64>,
+    },
+}
+
+/// Runs `cmd` against `rpc`. Checks of many endpoints can run concurrently
+/// on one runtime; see [`blocking::check`] for a synchronous call.
+pub async fn check(cmd: Command, rpc: &str, ctx: &Context) -> CheckResult {
+    let mut result = context::measure(run(cmd, rpc, ctx)).await;
+
+    result.insecure = ctx.trust.insecure;
+    result
+}
+
+/// Runs `cmd` without measuring it, so detection and the check it leads to
+/// are timed as one.
+pub(crate) async fn run(cmd: Command, rpc: &str, ctx: &Context) -> CheckResult {
     match cmd {
-        Command::Tendermint(m) => check_tendermint(m, rpc),
-        Command::Ethereum(m) => check_ethereum(m, rpc),
-        Command::Bitcoin(m) => check_bitcoin(m, rpc),
+        Command::Tendermint(m) => check_tendermint(m, rpc, ctx).await,
+        Command::Ethereum(m) => check_ethereum(m, rpc, ctx).await,
+        Command::Bitcoin(m) => check_bitcoin(m, rpc, ctx).await,
+        Command::Engine(m) => check_engine(m, rpc, ctx).await,
+        Command::Auto(auto) => Box::pin(detect::check(auto, rpc, ctx)).await,
     }
 }
 
-fn check_tendermint(method: TendermintMethod, rpc: &str) -> CheckResult {
+/// Synchronous wrappers for callers without a tokio runtime.
+pub mod blocking {
+    use crate::context::Context;
+    use crate::types::{CheckResult, Command};
+
+    /// Runs [`super::check`] to completion on a runtime of its own. Must not
+    /// be called from within an async context.
+    pub fn check(cmd: Command, rpc: &str, ctx: &Context) -> CheckResult {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start the tokio runtime")
+            .block_on(super::check(cmd, rpc, ctx))
+    }
+}
+
+async fn check_tendermint(method: TendermintMethod, rpc: &str, ctx: &Context) -> CheckResult {
+    let mut transport = match Transport::connect(rpc, ctx).await {
+        Ok(t) => t,
+        Err(e) => return unreachable_result("tendermint", rpc, e),
+    };
+    let transport = &mut transport;
+
     match method {
-        TendermintMethod::Status => check_tendermint_status(rpc),
-        TendermintMethod::Health => check_tendermint_health(rpc),
-        TendermintMethod::Block { height } => check_tendermint_block(rpc, height),
+        TendermintMethod::Status => check_tendermint_status(transport, rpc).await,
+        TendermintMethod::Health => check_tendermint_health(transport, rpc).await,
+        TendermintMethod::Block { height } => check_tendermint_block(transport, rpc, height).await,
+        TendermintMethod::Depth => check_tendermint_depth(transport, rpc).await,
+        TendermintMethod::Audit => check_tendermint_audit(transport, rpc).await,
+        TendermintMethod::Subscription => check_tendermint_subscription(transport, rpc).await,
+        TendermintMethod::Subscribe { duration } => {
+            check_tendermint_delivery(transport, rpc, ctx, duration).await
+        }
     }
 }
 
-fn check_ethereum(method: EthereumMethod, rpc: &str) -> CheckResult {
+async fn check_ethereum(method: EthereumMethod, rpc: &str, ctx: &Context) -> CheckResult {
+    let mut transport = match Transport::connect(rpc, ctx).await {
+        Ok(t) => t,
+        Err(e) => return unreachable_result("ethereum", rpc, e),
+    };
+    let transport = &mut transport;
+
     match method {
-        EthereumMethod::Status => check_ethereum_status(rpc),
-        EthereumMethod::Health => check_ethereum_health(rpc),
-        EthereumMethod::Block { height } => check_ethereum_block(rpc, height),
+        EthereumMethod::Status => check_ethereum_status(transport, rpc).await,
+        EthereumMethod::Health => check_ethereum_health(transport, rpc).await,
+        EthereumMethod::Block { height } => check_ethereum_block(transport, rpc, height).await,
+        EthereumMethod::Archive { height } => check_ethereum_archive(transport, rpc, height).await,
+        EthereumMethod::Depth => check_ethereum_depth(transport, rpc).await,
+        EthereumMethod::Capabilities => check_ethereum_capabilities(transport, rpc).await,
+        EthereumMethod::Logs => check_ethereum_logs(transport, rpc).await,
+        EthereumMethod::Audit => check_ethereum_audit(transport, rpc).await,
+        EthereumMethod::Subscription => check_ethereum_subscription(transport, rpc).await,
+        EthereumMethod::Subscribe { duration } => {
+            check_ethereum_delivery(transport, rpc, ctx, duration).await
+        }
     }
 }
 
-fn check_bitcoin(method: BitcoinMethod, rpc: &str) -> CheckResult {
+async fn check_bitcoin(method: BitcoinMethod, rpc: &str, ctx: &Context) -> CheckResult {
     match method {
-        BitcoinMethod::Status => check_bitcoin_status(rpc),
-        BitcoinMethod::Health => check_bitcoin_health(rpc),
-        BitcoinMethod::Block { height } => check_bitcoin_block(rpc, height),
+        BitcoinMethod::Status => check_bitcoin_status(rpc, ctx).await,
+        BitcoinMethod::Health => check_bitcoin_health(rpc, ctx).await,
+        BitcoinMethod::Block { height } => check_bitcoin_block(rpc, ctx, height).await,
+        BitcoinMethod::Depth => check_bitcoin_depth(rpc, ctx).await,
+        BitcoinMethod::Audit => check_bitcoin_audit(rpc, ctx).await,
     }
 }
 
-fn check_tendermint_status(rpc: &str) -> CheckResult {
-    let status_url = format!("{}/status", rpc);
+async fn check_engine(method: EngineMethod, rpc: &str, ctx: &Context) -> CheckResult {
+    let mut transport = match Transport::connect(rpc, ctx).await {
+        Ok(t) => t,
+        Err(e) => return unreachable_result("engine", rpc, e),
+    };
+    let transport = &mut transport;
 
-    let resp = reqwest::blocking::get(&status_url);
+    match method {
+        EngineMethod::Status => check_engine_status(transport, rpc).await,
+    }
+}
 
-    match resp {
-        Ok(response) => {
-            if !response.status().is_success() {
-                return CheckResult {
-                    protocol: "tendermint".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: None,
-                    error: Some(format!("HTTP {}", response.status())),
-                };
-            }
+fn unreachable_result(protocol: &str, rpc: &str, err: CallError) -> CheckResult {
+    CheckResult {
+        protocol: protocol.to_string(),
+        rpc: rpc.to_string(),
+        reachable: false,
+        result: None,
+        error: Some(err.to_string()),
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
 
-            let json: Result<serde_json::Value, _> = response.json();
-            match json {
-                Ok(v) => {
-                    let sync_info = &v["result"]["sync_info"];
+async fn check_tendermint_status(transport: &mut Transport, rpc: &str) -> CheckResult {
+    match transport.route("status", &[]).await {
+        Ok(v) => {
+            let sync_info = &v["sync_info"];
 
-                    let latest_block = sync_info["latest_block_height"]
-                        .as_str()
-                        .and_then(|s| s.parse::<u64>().ok());
+            let latest_block = sync_info["latest_block_height"]
+                .as_str()
+                .and_then(|s| s.parse::<u64>().ok());
 
-                    let catching_up = sync_info["catching_up"].as_bool();
+            let catching_up = sync_info["catching_up"].as_bool();
 
-                    CheckResult {
-                        protocol: "tendermint".to_string(),
-                        rpc: rpc.to_string(),
-                        reachable: true,
-                        result: Some(ResultData::Status {
-                            latest_block,
-                            syncing: catching_up,
-                        }),
-                        error: None,
-                    }
-                }
-                Err(e) => CheckResult {
-                    protocol: "tendermint".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: None,
-                    error: Some(format!("Invalid JSON: {}", e)),
-                },
+            CheckResult {
+                protocol: "tendermint".to_string(),
+                rpc: rpc.to_string(),
+                reachable: true,
+                result: Some(ResultData::Status {
+                    latest_block,
+                    syncing: catching_up,
+                }),
+                error: None,
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
             }
         }
-        Err(e) => CheckResult {
-            protocol: "tendermint".to_string(),
-            rpc: rpc.to_string(),
-            reachable: false,
-            result: None,
-            error: Some(e.to_string()),
-        },
+        Err(e) => unreachable_result("tendermint", rpc, e),
     }
 }
 
-fn check_tendermint_health(rpc: &str) -> CheckResult {
-    let url = format!("{}/health", rpc);
-
-    let resp = reqwest::blocking::get(&url);
-
-    match resp {
-        Ok(response) => {
-            if response.status().is_success() {
-                CheckResult {
-                    protocol: "tendermint".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: true,
-                    result: Some(ResultData::Health { healthy: true }),
-                    error: None,
-                }
-            } else {
-                CheckResult {
-                    protocol: "tendermint".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: Some(ResultData::Health { healthy: false }),
-                    error: Some(format!("HTTP {}", response.status())),
-                }
-            }
-        }
+async fn check_tendermint_health(transport: &mut Transport, rpc: &str) -> CheckResult {
+    // `/health` answers with an empty body on some nodes and proxies; any
+    // successful response counts.
+    match transport.route("health", &[]).await {
+        Ok(_) | Err(CallError::InvalidJson(_)) => CheckResult {
+            protocol: "tendermint".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Health { healthy: true }),
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
         Err(e) => CheckResult {
             protocol: "tendermint".to_string(),
             rpc: rpc.to_string(),
             reachable: false,
             result: Some(ResultData::Health { healthy: false }),
             error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
     }
 }
 
-fn check_tendermint_block(rpc: &str, height: Option<u64>) -> CheckResult {
-    let url = match height {
-        Some(h) => format!("{}/block?height={}", rpc, h),
-        None => format!("{}/block", rpc),
-    };
-
-    let resp = reqwest::blocking::get(&url);
-
-    match resp {
-        Ok(response) => {
-            if !response.status().is_success() {
-                return CheckResult {
-                    protocol: "tendermint".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: None,
-                    error: Some(format!("HTTP {}", response.status())),
-                };
-            }
-
-            let json: Result<serde_json::Value, _> = response.json();
-            match json {
-                Ok(v) => {
-                    let height = v["result"]["block"]["header"]["height"]
-                        .as_str()
-                        .and_then(|s| s.parse::<u64>().ok());
-
-                    CheckResult {
-                        protocol: "tendermint".to_string(),
-                        rpc: rpc.to_string(),
-                        reachable: true,
-                        result: Some(ResultData::Block { height }),
-                        error: None,
-                    }
-                }
-                Err(e) => CheckResult {
-                    protocol: "tendermint".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: None,
-                    error: Some(format!("Invalid JSON: {}", e)),
-                },
-            }
-        }
-        Err(e) => CheckResult {
+async fn check_tendermint_block(
+    transport: &mut Transport,
+    rpc: &str,
+    height: Option<u64>,
+) -> CheckResult {
+    match fetch_tendermint_block(transport, height).await {
+        Ok(height) => CheckResult {
             protocol: "tendermint".to_string(),
             rpc: rpc.to_string(),
-            reachable: false,
-            result: None,
-            error: Some(e.to_string()),
+            reachable: true,
+            result: Some(ResultData::Block { height }),
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
+        Err(e) => unreachable_result("tendermint", rpc, e),
     }
 }
 
-fn check_ethereum_status(rpc: &str) -> CheckResult {
-    let client = reqwest::blocking::Client::new();
-
-    let syncing_req = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "eth_syncing",
-        "params": [],
-        "id": 1
-    });
-
-    let syncing_resp = client.post(rpc).json(&syncing_req).send();
-    let syncing_resp = match syncing_resp {
-        Ok(r) => r,
-        Err(e) => {
-            return CheckResult {
-                protocol: "ethereum".to_string(),
-                rpc: rpc.to_string(),
-                reachable: false,
-                result: None,
-                error: Some(e.to_string()),
-            };
-        }
-    };
-
-    let syncing_json: serde_json::Value = match syncing_resp.json() {
-        Ok(v) => v,
-        Err(e) => {
-            return CheckResult {
-                protocol: "ethereum".to_string(),
-                rpc: rpc.to_string(),
-                reachable: false,
-                result: None,
-                error: Some(format!("Invalid JSON: {}", e)),
-            };
-        }
+/// Fetches a Tendermint block (the latest if no height is given) and returns
+/// the height in its header.
+async fn fetch_tendermint_block(
+    transport: &mut Transport,
+    height: Option<u64>,
+) -> Result<Option<u64>, CallError> {
+    let params = match height {
+        Some(h) => vec![("height", h.to_string())],
+        None => vec![],
     };
 
-    let syncing = syncing_json["result"].is_object();
-
-    let block_req = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "eth_blockNumber",
-        "params": [],
-        "id": 2
-    });
+    let v = transport.route("block", &params).await?;
+    Ok(v["block"]["header"]["height"]
+        .as_str()
+        .and_then(|s| s.parse::<u64>().ok()))
+}
 
-    let block_resp = client.post(rpc).json(&block_req).send();
-    let block_resp = match block_resp {
-        Ok(r) => r,
-        Err(e) => {
-            return CheckResult {
-                protocol: "ethereum".to_string(),
-                rpc: rpc.to_string(),
-                reachable: false,
-                result: None,
-                error: Some(e.to_string()),
-            };
+async fn check_ethereum_status(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let results = transport
+        .batch(vec![
+            ("eth_syncing", serde_json::json!([])),
+            ("eth_blockNumber", serde_json::json!([])),
+        ])
+        .await;
+    let [syncing, latest_block]: [_; 2] = results.try_into().expect("one result per call");
+
+    // A node that answers with a JSON-RPC error is still reachable; it just
+    // reports nothing for that call.
+    let mut error = None;
+
+    let syncing = match syncing {
+        Ok(v) => v.is_object(),
+        Err(e @ CallError::Rpc { .. }) => {
+            error = Some(e.to_string());
+            false
         }
+        Err(e) => return unreachable_result("ethereum", rpc, e),
     };
 
-    let block_json: serde_json::Value = match block_resp.json() {
-        Ok(v) => v,
-        Err(e) => {
-            return CheckResult {
-                protocol: "ethereum".to_string(),
-                rpc: rpc.to_string(),
-                reachable: false,
-                result: None,
-                error: Some(format!("Invalid JSON: {}", e)),
-            };
+    let latest_block = match latest_block {
+        Ok(v) => v
+            .as_str()
+            .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()),
+        Err(e @ CallError::Rpc { .. }) => {
+            error = Some(e.to_string());
+            None
         }
+        Err(e) => return unreachable_result("ethereum", rpc, e),
     };
 
-    let latest_block = block_json["result"]
-        .as_str()
-        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok());
-
     CheckResult {
         protocol: "ethereum".to_string(),
         rpc: rpc.to_string(),
@@ -266,132 +310,219 @@ fn check_ethereum_status(rpc: &str) -> CheckResult {
             latest_block,
             syncing: Some(syncing),
         }),
-        error: None,
+        error,
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
     }
 }
 
-fn check_ethereum_health(rpc: &str) -> CheckResult {
-    let client = reqwest::blocking::Client::new();
-
-    let req = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "eth_chainId",
-        "params": [],
-        "id": 1
-    });
-
-    let resp = client.post(rpc).json(&req).send();
-
-    match resp {
-        Ok(response) => {
-            if !response.status().is_success() {
-                return CheckResult {
-                    protocol: "ethereum".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: Some(ResultData::Health { healthy: false }),
-                    error: Some(format!("HTTP {}", response.status())),
-                };
-            }
-
-            let json: Result<serde_json::Value, _> = response.json();
-            match json {
-                Ok(v) => {
-                    let healthy = v.get("result").is_some();
-
-                    CheckResult {
-                        protocol: "ethereum".to_string(),
-                        rpc: rpc.to_string(),
-                        reachable: healthy,
-                        result: Some(ResultData::Health { healthy }),
-                        error: None,
-                    }
-                }
-                Err(e) => CheckResult {
-                    protocol: "ethereum".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: Some(ResultData::Health { healthy: false }),
-                    error: Some(format!("Invalid JSON: {}", e)),
-                },
-            }
-        }
+async fn check_ethereum_health(transport: &mut Transport, rpc: &str) -> CheckResult {
+    match transport.call("eth_chainId", serde_json::json!([])).await {
+        Ok(_) => CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Health { healthy: true }),
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+        Err(e @ CallError::Rpc { .. }) => CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Health { healthy: false }),
+            error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
         Err(e) => CheckResult {
             protocol: "ethereum".to_string(),
             rpc: rpc.to_string(),
             reachable: false,
             result: Some(ResultData::Health { healthy: false }),
             error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
     }
 }
 
-fn check_ethereum_block(rpc: &str, height: Option<u64>) -> CheckResult {
-    let client = reqwest::blocking::Client::new();
+async fn check_ethereum_block(
+    transport: &mut Transport,
+    rpc: &str,
+    height: Option<u64>,
+) -> CheckResult {
+    match fetch_ethereum_block(transport, height).await {
+        Ok(height) => CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: rpc.to_string(),
+            reachable: height.is_some(),
+            result: Some(ResultData::Block { height }),
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+        Err(e) => unreachable_result("ethereum", rpc, e),
+    }
+}
 
+/// Fetches an Ethereum block header (the latest if no height is given) and
+/// returns its number; `None` when the node answers `null` for it.
+async fn fetch_ethereum_block(
+    transport: &mut Transport,
+    height: Option<u64>,
+) -> Result<Option<u64>, CallError> {
     let block_param = match height {
         Some(n) => format!("0x{:x}", n),
         None => "latest".to_string(),
     };
 
-    let req = serde_json::json!({
-        "jsonrpc": "2.0",
-        "method": "eth_getBlockByNumber",
-        "params": [block_param, false],
-        "id": 1
-    });
+    let params = serde_json::json!([block_param, false]);
 
-    let resp = client.post(rpc).json(&req).send();
-
-    match resp {
-        Ok(response) => {
-            if !response.status().is_success() {
-                return CheckResult {
-                    protocol: "ethereum".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: None,
-                    error: Some(format!("HTTP {}", response.status())),
-                };
-            }
+    let v = transport.call("eth_getBlockByNumber", params).await?;
+    Ok(v["number"]
+        .as_str()
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()))
+}
 
-            let json: Result<serde_json::Value, _> = response.json();
-            match json {
-                Ok(v) => {
-                    let height = v["result"]["number"]
-                        .as_str()
-                        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok());
+async fn check_ethereum_archive(
+    transport: &mut Transport,
+    rpc: &str,
+    height: Option<u64>,
+) -> CheckResult {
+    let block = height.unwrap_or(DEFAULT_ARCHIVE_BLOCK);
+    let block_param = format!("0x{:x}", block);
+
+    let header = transport
+        .call(
+            "eth_getBlockByNumber",
+            serde_json::json!([block_param, false]),
+        )
+        .await;
+
+    match header {
+        Ok(v) if v.is_null() => {
+            return CheckResult {
+                protocol: "ethereum".to_string(),
+                rpc: rpc.to_string(),
+                reachable: true,
+                result: Some(ResultData::Archive {
+                    block,
+                    node: Some(NodeKind::Pruned),
+                }),
+                error: None,
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
+            };
+        }
+        Ok(_) => {}
+        Err(e) => return archive_error(rpc, block, e),
+    }
 
-                    CheckResult {
-                        protocol: "ethereum".to_string(),
-                        rpc: rpc.to_string(),
-                        reachable: height.is_some(),
-                        result: Some(ResultData::Block { height }),
-                        error: None,
-                    }
-                }
-                Err(e) => CheckResult {
-                    protocol: "ethereum".to_string(),
-                    rpc: rpc.to_string(),
-                    reachable: false,
-                    result: None,
-                    error: Some(format!("Invalid JSON: {}", e)),
-                },
-            }
+    let probes = [
+        (
+            "eth_getBalance",
+            serde_json::json!([ZERO_ADDRESS, block_param]),
+        ),
+        (
+            "eth_getCode",
+            serde_json::json!([ZERO_ADDRESS, block_param]),
+        ),
+    ];
+
+    for outcome in transport.batch(probes.to_vec()).await {
+        if let Err(e) = outcome {
+            return archive_error(rpc, block, e);
         }
-        Err(e) => CheckResult {
+    }
+
+    CheckResult {
+        protocol: "ethereum".to_string(),
+        rpc: rpc.to_string(),
+        reachable: true,
+        result: Some(ResultData::Archive {
+            block,
+            node: Some(NodeKind::Archive),
+        }),
+        error: None,
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
+
+/// What a historical-state error says about the node, if it is one of the
+/// known missing-block or missing-state messages.
+fn missing_history(message: &str) -> Option<NodeKind> {
+    let m = message.to_lowercase();
+
+    if MISSING_BLOCK_ERRORS.iter().any(|p| m.contains(p)) {
+        Some(NodeKind::Pruned)
+    } else if MISSING_STATE_ERRORS.iter().any(|p| m.contains(p)) {
+        Some(NodeKind::Full)
+    } else {
+        None
+    }
+}
+
+fn archive_error(rpc: &str, block: u64, err: CallError) -> CheckResult {
+    let node = err.rpc_message().and_then(missing_history);
+
+    match (node, err) {
+        (Some(node), _) => CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Archive {
+                block,
+                node: Some(node),
+            }),
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+        (None, e @ CallError::Rpc { .. }) => CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Archive { block, node: None }),
+            error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+        (None, e) => CheckResult {
             protocol: "ethereum".to_string(),
             rpc: rpc.to_string(),
             reachable: false,
             result: None,
             error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
     }
 }
 
-fn check_bitcoin_status(rpc: &str) -> CheckResult {
-    let client = reqwest::blocking::Client::new();
-
+async fn check_bitcoin_status(rpc: &str, ctx: &Context) -> CheckResult {
     let req = serde_json::json!({
         "jsonrpc": "1.0",
         "id": "rpc-checker",
@@ -399,11 +530,14 @@ fn check_bitcoin_status(rpc: &str) -> CheckResult {
         "params": []
     });
 
-    let resp = client
-        .post(rpc)
-        .basic_auth("rpcuser", Some("rpcpass"))
-        .json(&req)
-        .send();
+    let resp = context::send(
+        ctx.client
+            .post(rpc)
+            .basic_auth("rpcuser", Some("rpcpass"))
+            .headers(ctx.auth.header_map())
+            .json(&req),
+    )
+    .await;
 
     match resp {
         Ok(response) => {
@@ -414,10 +548,14 @@ fn check_bitcoin_status(rpc: &str) -> CheckResult {
                     reachable: false,
                     result: None,
                     error: Some(format!("HTTP {}", response.status())),
+                    tls: None,
+                    timings: None,
+                    insecure: false,
+                    labels: None,
                 };
             }
 
-            let json: Result<serde_json::Value, _> = response.json();
+            let json: Result<serde_json::Value, _> = response.json().await;
             match json {
                 Ok(v) => {
                     let result = &v["result"];
@@ -438,6 +576,10 @@ fn check_bitcoin_status(rpc: &str) -> CheckResult {
                             syncing,
                         }),
                         error: None,
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     }
                 }
                 Err(e) => CheckResult {
@@ -446,6 +588,10 @@ fn check_bitcoin_status(rpc: &str) -> CheckResult {
                     reachable: false,
                     result: None,
                     error: Some(format!("Invalid JSON: {}", e)),
+                    tls: None,
+                    timings: None,
+                    insecure: false,
+                    labels: None,
                 },
             }
         }
@@ -455,13 +601,15 @@ fn check_bitcoin_status(rpc: &str) -> CheckResult {
             reachable: false,
             result: None,
             error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
     }
 }
 
-fn check_bitcoin_health(rpc: &str) -> CheckResult {
-    let client = reqwest::blocking::Client::new();
-
+async fn check_bitcoin_health(rpc: &str, ctx: &Context) -> CheckResult {
     let req = serde_json::json!({
         "jsonrpc": "1.0",
         "id": "rpc-checker",
@@ -469,11 +617,14 @@ fn check_bitcoin_health(rpc: &str) -> CheckResult {
         "params": []
     });
 
-    let resp = client
-        .post(rpc)
-        .basic_auth("rpcuser", Some("rpcpass"))
-        .json(&req)
-        .send();
+    let resp = context::send(
+        ctx.client
+            .post(rpc)
+            .basic_auth("rpcuser", Some("rpcpass"))
+            .headers(ctx.auth.header_map())
+            .json(&req),
+    )
+    .await;
 
     match resp {
         Ok(response) => {
@@ -484,10 +635,14 @@ fn check_bitcoin_health(rpc: &str) -> CheckResult {
                     reachable: false,
                     result: Some(ResultData::Health { healthy: false }),
                     error: Some(format!("HTTP {}", response.status())),
+                    tls: None,
+                    timings: None,
+                    insecure: false,
+                    labels: None,
                 };
             }
 
-            let json: Result<serde_json::Value, _> = response.json();
+            let json: Result<serde_json::Value, _> = response.json().await;
             match json {
                 Ok(v) => {
                     let healthy = v.get("result").is_some();
@@ -498,6 +653,10 @@ fn check_bitcoin_health(rpc: &str) -> CheckResult {
                         reachable: healthy,
                         result: Some(ResultData::Health { healthy }),
                         error: None,
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     }
                 }
                 Err(e) => CheckResult {
@@ -506,6 +665,10 @@ fn check_bitcoin_health(rpc: &str) -> CheckResult {
                     reachable: false,
                     result: Some(ResultData::Health { healthy: false }),
                     error: Some(format!("Invalid JSON: {}", e)),
+                    tls: None,
+                    timings: None,
+                    insecure: false,
+                    labels: None,
                 },
             }
         }
@@ -515,13 +678,15 @@ fn check_bitcoin_health(rpc: &str) -> CheckResult {
             reachable: false,
             result: Some(ResultData::Health { healthy: false }),
             error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
     }
 }
 
-fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
-    let client = reqwest::blocking::Client::new();
-
+async fn check_bitcoin_block(rpc: &str, ctx: &Context, height: Option<u64>) -> CheckResult {
     let height = match height {
         Some(h) => h,
         None => {
@@ -532,11 +697,14 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                 "params": []
             });
 
-            let info_resp = client
-                .post(rpc)
-                .basic_auth("rpcuser", Some("rpcpass"))
-                .json(&info_req)
-                .send();
+            let info_resp = context::send(
+                ctx.client
+                    .post(rpc)
+                    .basic_auth("rpcuser", Some("rpcpass"))
+                    .headers(ctx.auth.header_map())
+                    .json(&info_req),
+            )
+            .await;
 
             let info_resp = match info_resp {
                 Ok(r) if r.status().is_success() => r,
@@ -547,6 +715,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                         reachable: false,
                         result: None,
                         error: Some(format!("HTTP {}", r.status())),
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     };
                 }
                 Err(e) => {
@@ -556,11 +728,15 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                         reachable: false,
                         result: None,
                         error: Some(e.to_string()),
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     };
                 }
             };
 
-            let info_json: serde_json::Value = match info_resp.json() {
+            let info_json: serde_json::Value = match info_resp.json().await {
                 Ok(v) => v,
                 Err(e) => {
                     return CheckResult {
@@ -569,6 +745,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                         reachable: false,
                         result: None,
                         error: Some(format!("Invalid JSON: {}", e)),
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     };
                 }
             };
@@ -582,6 +762,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                         reachable: false,
                         result: None,
                         error: Some("Missing latest block height".to_string()),
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     };
                 }
             }
@@ -595,11 +779,14 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
         "params": [height]
     });
 
-    let hash_resp = client
-        .post(rpc)
-        .basic_auth("rpcuser", Some("rpcpass"))
-        .json(&hash_req)
-        .send();
+    let hash_resp = context::send(
+        ctx.client
+            .post(rpc)
+            .basic_auth("rpcuser", Some("rpcpass"))
+            .headers(ctx.auth.header_map())
+            .json(&hash_req),
+    )
+    .await;
 
     let hash_resp = match hash_resp {
         Ok(r) if r.status().is_success() => r,
@@ -610,6 +797,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                 reachable: false,
                 result: None,
                 error: Some(format!("HTTP {}", r.status())),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
             };
         }
         Err(e) => {
@@ -619,11 +810,15 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                 reachable: false,
                 result: None,
                 error: Some(e.to_string()),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
             };
         }
     };
 
-    let hash_json: serde_json::Value = match hash_resp.json() {
+    let hash_json: serde_json::Value = match hash_resp.json().await {
         Ok(v) => v,
         Err(e) => {
             return CheckResult {
@@ -632,6 +827,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                 reachable: false,
                 result: None,
                 error: Some(format!("Invalid JSON: {}", e)),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
             };
         }
     };
@@ -645,6 +844,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                 reachable: false,
                 result: None,
                 error: Some("Missing block hash".to_string()),
+                tls: None,
+                timings: None,
+                insecure: false,
+                labels: None,
             };
         }
     };
@@ -656,11 +859,14 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
         "params": [block_hash]
     });
 
-    let header_resp = client
-        .post(rpc)
-        .basic_auth("rpcuser", Some("rpcpass"))
-        .json(&header_req)
-        .send();
+    let header_resp = context::send(
+        ctx.client
+            .post(rpc)
+            .basic_auth("rpcuser", Some("rpcpass"))
+            .headers(ctx.auth.header_map())
+            .json(&header_req),
+    )
+    .await;
 
     match header_resp {
         Ok(response) => {
@@ -671,10 +877,14 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                     reachable: false,
                     result: None,
                     error: Some(format!("HTTP {}", response.status())),
+                    tls: None,
+                    timings: None,
+                    insecure: false,
+                    labels: None,
                 };
             }
 
-            let json: Result<serde_json::Value, _> = response.json();
+            let json: Result<serde_json::Value, _> = response.json().await;
             match json {
                 Ok(v) => {
                     let height = v["result"]["height"].as_u64();
@@ -685,6 +895,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                         reachable: height.is_some(),
                         result: Some(ResultData::Block { height }),
                         error: None,
+                        tls: None,
+                        timings: None,
+                        insecure: false,
+                        labels: None,
                     }
                 }
                 Err(e) => CheckResult {
@@ -693,6 +907,10 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
                     reachable: false,
                     result: None,
                     error: Some(format!("Invalid JSON: {}", e)),
+                    tls: None,
+                    timings: None,
+                    insecure: false,
+                    labels: None,
                 },
             }
         }
@@ -702,6 +920,193 @@ fn check_bitcoin_block(rpc: &str, height: Option<u64>) -> CheckResult {
             reachable: false,
             result: None,
             error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
         },
     }
 }
+
+async fn check_tendermint_depth(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let status = check_tendermint_status(transport, rpc).await;
+
+    check_depth(status, 1, async |height| {
+        match fetch_tendermint_block(transport, Some(height)).await {
+            Ok(_) => Ok(Probe::Available),
+            Err(e) if e.is_rate_limited() => Err(e),
+            Err(CallError::Rpc { message, .. }) => Ok(Probe::Unavailable {
+                lowest: parse_lowest_height(&message),
+            }),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+}
+
+async fn check_ethereum_depth(transport: &mut Transport, rpc: &str) -> CheckResult {
+    let status = check_ethereum_status(transport, rpc).await;
+
+    // Genesis is kept even by nodes that expire history, so the search
+    // starts at block 1 and genesis only counts once block 1 is there too.
+    let mut result = check_depth(status, 1, async |height| {
+        ethereum_probe(transport, height).await
+    })
+    .await;
+
+    if let Some(ResultData::Depth {
+        earliest_block: earliest_block @ 1,
+        probes,
+        ..
+    }) = &mut result.result
+    {
+        *probes += 1;
+        match ethereum_probe(transport, 0).await {
+            Ok(Probe::Available) => *earliest_block = 0,
+            Ok(Probe::Unavailable { .. }) => {}
+            Err(e) => {
+                return CheckResult {
+                    reachable: false,
+                    result: None,
+                    error: Some(e.to_string()),
+                    ..result
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Whether an Ethereum node serves `height`. Rate limiting aborts the search
+/// rather than passing for a missing block.
+async fn ethereum_probe(transport: &mut Transport, height: u64) -> Result<Probe, CallError> {
+    match fetch_ethereum_block(transport, Some(height)).await {
+        Ok(Some(_)) => Ok(Probe::Available),
+        Ok(None) => Ok(Probe::Unavailable { lowest: None }),
+        Err(e) if e.is_rate_limited() => Err(e),
+        Err(CallError::Rpc { .. }) => Ok(Probe::Unavailable { lowest: None }),
+        Err(e) => Err(e),
+    }
+}
+
+async fn check_bitcoin_depth(rpc: &str, ctx: &Context) -> CheckResult {
+    let status = check_bitcoin_status(rpc, ctx).await;
+
+    // getblockstats reads the block from disk, so it fails with "Block not
+    // available (pruned data)" where getblockhash/getblockheader would not.
+    check_depth(status, 0, async |height| {
+        let params = serde_json::json!([height, ["height"]]);
+
+        match jsonrpc::call_bitcoin(&ctx.client, rpc, &ctx.auth, "getblockstats", params).await {
+            Ok(_) => Ok(Probe::Available),
+            Err(e) if e.is_rate_limited() => Err(e),
+            Err(CallError::Rpc { .. }) => Ok(Probe::Unavailable { lowest: None }),
+            Err(e) => Err(e),
+        }
+    })
+    .await
+}
+
+/// Binary-searches `[floor, latest]` for the earliest block `probe` reports as
+/// available, taking the latest block from a prior status check.
+async fn check_depth(
+    status: CheckResult,
+    floor: u64,
+    probe: impl AsyncFnMut(u64) -> Result<Probe, CallError>,
+) -> CheckResult {
+    let latest_block = match &status.result {
+        Some(ResultData::Status {
+            latest_block: Some(h),
+            ..
+        }) => *h,
+        _ => {
+            return CheckResult {
+                result: None,
+                error: status
+                    .error
+                    .or_else(|| Some("Missing latest block height".to_string())),
+                ..status
+            };
+        }
+    };
+
+    match find_earliest_block(floor, latest_block, probe).await {
+        Ok((earliest_block, probes)) => CheckResult {
+            result: Some(ResultData::Depth {
+                latest_block,
+                earliest_block,
+                probes,
+            }),
+            ..status
+        },
+        Err(e) => CheckResult {
+            reachable: false,
+            result: None,
+            error: Some(e.to_string()),
+            ..status
+        },
+    }
+}
+
+async fn find_earliest_block(
+    floor: u64,
+    latest: u64,
+    mut probe: impl AsyncFnMut(u64) -> Result<Probe, CallError>,
+) -> Result<(u64, u32), CallError> {
+    let mut probes = 0;
+    let mut lo = floor;
+    let mut hi = latest;
+
+    // Nodes that report their lowest height let us skip the search entirely.
+    loop {
+        probes += 1;
+        match probe(lo).await? {
+            Probe::Available => return Ok((lo, probes)),
+            Probe::Unavailable { lowest: Some(l) } if l > lo && l <= hi => lo = l,
+            Probe::Unavailable { .. } => break,
+        }
+    }
+
+    // `lo` is unavailable and `hi` (the tip) is assumed available.
+    while hi > lo + 1 {
+        let mid = lo + (hi - lo) / 2;
+        probes += 1;
+        match probe(mid).await? {
+            Probe::Available => hi = mid,
+            Probe::Unavailable { .. } => lo = mid,
+        }
+    }
+
+    Ok((hi, probes))
+}
+
+fn parse_lowest_height(message: &str) -> Option<u64> {
+    let (_, rest) = message.split_once("lowest height is ")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_history_messages() {
+        assert_eq!(
+            missing_history("missing trie node abc (path )"),
+            Some(NodeKind::Full)
+        );
+        assert_eq!(
+            missing_history("historical state not available in path scheme yet"),
+            Some(NodeKind::Full)
+        );
+        assert_eq!(missing_history("header not found"), Some(NodeKind::Pruned));
+        assert_eq!(missing_history("block pruned"), Some(NodeKind::Pruned));
+        assert_eq!(
+            missing_history("Pruned block: history expired"),
+            Some(NodeKind::Pruned)
+        );
+        assert_eq!(missing_history("execution reverted"), None);
+    }
+}
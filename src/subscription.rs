@@ -0,0 +1,338 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+use tokio::time::Instant;
+
+use crate::context::Context;
+use crate::jsonrpc::CallError;
+use crate::transport::Transport;
+use crate::types::{CheckResult, DeliveryQuality, ResultData};
+
+/// How long to wait for the first event after subscribing. Comfortably above
+/// Ethereum's 12s slots and typical Tendermint block times.
+const FIRST_EVENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often `subscribe` polls the latest height to spot missed events.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pause between reconnection attempts after the socket drops.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Latest height as polled over the subscription's connection.
+type LatestHeight<'a> = BoxFuture<'a, Result<Option<u64>, CallError>>;
+
+/// A new-block subscription and how to read heights from it.
+struct Feed {
+    event: &'static str,
+    method: &'static str,
+    params: Value,
+    /// Extracts the block height from a notification.
+    height: fn(&Value) -> Option<u64>,
+    /// Polls the latest height over the same connection.
+    latest: fn(&mut Transport) -> LatestHeight<'_>,
+}
+
+fn ethereum_feed() -> Feed {
+    Feed {
+        event: "newHeads",
+        method: "eth_subscribe",
+        params: json!(["newHeads"]),
+        height: |msg| parse_hex(&msg["params"]["result"]["number"]),
+        latest: ethereum_latest,
+    }
+}
+
+fn tendermint_feed() -> Feed {
+    Feed {
+        event: "NewBlock",
+        method: "subscribe",
+        params: json!({ "query": "tm.event='NewBlock'" }),
+        height: |msg| {
+            let header = &msg["result"]["data"]["value"]["block"]["header"];
+            parse_decimal(&header["height"])
+        },
+        latest: tendermint_latest,
+    }
+}
+
+fn ethereum_latest(transport: &mut Transport) -> LatestHeight<'_> {
+    Box::pin(async move {
+        transport
+            .call("eth_blockNumber", json!([]))
+            .await
+            .map(|v| parse_hex(&v))
+    })
+}
+
+fn tendermint_latest(transport: &mut Transport) -> LatestHeight<'_> {
+    Box::pin(async move {
+        transport
+            .route("status", &[])
+            .await
+            .map(|v| parse_decimal(&v["sync_info"]["latest_block_height"]))
+    })
+}
+
+pub async fn check_ethereum_subscription(transport: &mut Transport, rpc: &str) -> CheckResult {
+    check_subscription("ethereum", transport, rpc, &ethereum_feed()).await
+}
+
+pub async fn check_tendermint_subscription(transport: &mut Transport, rpc: &str) -> CheckResult {
+    check_subscription("tendermint", transport, rpc, &tendermint_feed()).await
+}
+
+pub async fn check_ethereum_delivery(
+    transport: &mut Transport,
+    rpc: &str,
+    ctx: &Context,
+    duration: Duration,
+) -> CheckResult {
+    check_delivery("ethereum", transport, rpc, ctx, &ethereum_feed(), duration).await
+}
+
+pub async fn check_tendermint_delivery(
+    transport: &mut Transport,
+    rpc: &str,
+    ctx: &Context,
+    duration: Duration,
+) -> CheckResult {
+    check_delivery(
+        "tendermint",
+        transport,
+        rpc,
+        ctx,
+        &tendermint_feed(),
+        duration,
+    )
+    .await
+}
+
+/// Subscribes and measures the time until the first notification arrives.
+async fn check_subscription(
+    protocol: &str,
+    transport: &mut Transport,
+    rpc: &str,
+    feed: &Feed,
+) -> CheckResult {
+    if let Err(e) = subscribe(transport, feed).await {
+        return CheckResult {
+            protocol: protocol.to_string(),
+            rpc: rpc.to_string(),
+            reachable: false,
+            result: None,
+            error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        };
+    }
+
+    let started = Instant::now();
+
+    match transport.next_notification(FIRST_EVENT_TIMEOUT).await {
+        Ok((arrived, msg)) => CheckResult {
+            protocol: protocol.to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Subscription {
+                event: feed.event.to_string(),
+                first_event_ms: Some(arrived.saturating_duration_since(started).as_millis() as u64),
+                height: (feed.height)(&msg),
+            }),
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+        Err(e) => CheckResult {
+            protocol: protocol.to_string(),
+            rpc: rpc.to_string(),
+            reachable: true,
+            result: Some(ResultData::Subscription {
+                event: feed.event.to_string(),
+                first_event_ms: None,
+                height: None,
+            }),
+            error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        },
+    }
+}
+
+/// Holds the subscription for `duration`, polling the latest height on the
+/// side, and reports gaps between events, heights that were never delivered
+/// and how often the socket had to be reopened.
+async fn check_delivery(
+    protocol: &str,
+    transport: &mut Transport,
+    rpc: &str,
+    ctx: &Context,
+    feed: &Feed,
+    duration: Duration,
+) -> CheckResult {
+    if let Err(e) = subscribe(transport, feed).await {
+        return CheckResult {
+            protocol: protocol.to_string(),
+            rpc: rpc.to_string(),
+            reachable: false,
+            result: None,
+            error: Some(e.to_string()),
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        };
+    }
+
+    let started = Instant::now();
+    let end = started + duration;
+
+    let mut received = BTreeSet::new();
+    let mut events = 0u64;
+    let mut gaps = Vec::new();
+    let mut last_event = started;
+    let mut first_polled = None;
+    let mut last_polled = None;
+    let mut settled_polled = None;
+    let mut next_poll = started;
+    let mut reconnections = 0u32;
+    let mut last_error = None;
+
+    while Instant::now() < end {
+        let now = Instant::now();
+
+        if now >= next_poll {
+            // The poll shares the connection, so it must not outlast the
+            // check; a call can otherwise wait up to 30s for its response.
+            let poll =
+                tokio::time::timeout(end.saturating_duration_since(now), (feed.latest)(transport));
+            if let Ok(Ok(Some(h))) = poll.await {
+                first_polled.get_or_insert(h);
+                last_polled = Some(h);
+                if now + POLL_INTERVAL <= end {
+                    settled_polled = Some(h);
+                }
+            }
+            next_poll = now + POLL_INTERVAL;
+        }
+
+        let wait = next_poll.min(end).saturating_duration_since(Instant::now());
+
+        match transport.next_notification(wait).await {
+            Ok((arrived, msg)) => {
+                if events > 0 {
+                    gaps.push(arrived.saturating_duration_since(last_event));
+                }
+                last_event = arrived;
+                events += 1;
+
+                if let Some(h) = (feed.height)(&msg) {
+                    received.insert(h);
+                }
+            }
+            Err(CallError::Timeout) => {}
+            Err(e) => {
+                last_error = Some(e.to_string());
+                tokio::time::sleep(RECONNECT_DELAY).await;
+
+                let reconnected = match Transport::connect(rpc, ctx).await {
+                    Ok(mut t) => subscribe(&mut t, feed).await.map(|_| t),
+                    Err(e) => Err(e),
+                };
+
+                match reconnected {
+                    Ok(t) => {
+                        *transport = t;
+                        reconnections += 1;
+                    }
+                    Err(e) => last_error = Some(e.to_string()),
+                }
+            }
+        }
+    }
+
+    // Heights polled at least one interval before the end should have been
+    // delivered by now; the event for the first polled height predates the
+    // subscription.
+    let horizon = received.last().copied().max(settled_polled);
+    let start = received
+        .first()
+        .copied()
+        .into_iter()
+        .chain(first_polled.map(|h| h + 1))
+        .min();
+    let missed_heights = match (start, horizon) {
+        (Some(start), Some(horizon)) => {
+            (start..=horizon).filter(|h| !received.contains(h)).count() as u64
+        }
+        _ => 0,
+    };
+
+    let max_gap_ms = gaps.iter().max().map(|d| d.as_millis() as u64);
+    let mean_gap_ms = if gaps.is_empty() {
+        None
+    } else {
+        Some((gaps.iter().sum::<Duration>() / gaps.len() as u32).as_millis() as u64)
+    };
+
+    let quality = if events == 0 {
+        DeliveryQuality::Failed
+    } else if missed_heights > 0 || reconnections > 0 {
+        DeliveryQuality::Degraded
+    } else {
+        DeliveryQuality::Good
+    };
+
+    CheckResult {
+        protocol: protocol.to_string(),
+        rpc: rpc.to_string(),
+        reachable: true,
+        result: Some(ResultData::Delivery {
+            event: feed.event.to_string(),
+            duration_secs: duration.as_secs(),
+            events,
+            first_height: received.first().copied(),
+            last_height: received.last().copied(),
+            latest_polled: last_polled,
+            missed_heights,
+            max_gap_ms,
+            mean_gap_ms,
+            reconnections,
+            quality,
+        }),
+        error: last_error,
+        tls: None,
+        timings: None,
+        insecure: false,
+        labels: None,
+    }
+}
+
+async fn subscribe(transport: &mut Transport, feed: &Feed) -> Result<(), CallError> {
+    if !transport.is_streaming() {
+        return Err(CallError::Request(
+            "Subscriptions require a ws://, wss:// or ipc:// endpoint".to_string(),
+        ));
+    }
+
+    transport
+        .call(feed.method, feed.params.clone())
+        .await
+        .map(|_| ())
+}
+
+fn parse_hex(v: &Value) -> Option<u64> {
+    v.as_str()
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+}
+
+fn parse_decimal(v: &Value) -> Option<u64> {
+    v.as_str().and_then(|s| s.parse::<u64>().ok())
+}
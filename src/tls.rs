@@ -0,0 +1,502 @@
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::Url;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::{WantsClientCert, WebPkiServerVerifier};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, ClientConnection, ConfigBuilder, DigitallySignedStruct,
+    ProtocolVersion, RootCertStore, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use time::format_description::well_known::Rfc3339;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::proxy::Proxy;
+use crate::types::{Certificate, TlsInfo};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// How HTTPS and WSS endpoints are trusted, and how checks authenticate to
+/// them.
+#[derive(Debug, Clone, Default)]
+pub struct Trust {
+    /// PEM bundles of CAs trusted alongside the built-in WebPKI roots.
+    pub ca_bundles: Vec<PathBuf>,
+    /// PEM certificate chain and private key presented for mutual TLS.
+    pub client_identity: Option<(PathBuf, PathBuf)>,
+    /// SPKI pins as `sha256//<base64>`; some certificate in the chain must
+    /// carry one of these public keys.
+    pub pins: Vec<String>,
+    /// Accepts any certificate chain. Pins are still enforced.
+    pub insecure: bool,
+}
+
+impl Trust {
+    /// Builds the TLS configuration checks connect with.
+    pub fn client_config(&self) -> Result<ClientConfig, String> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let inner =
+            WebPkiServerVerifier::builder_with_provider(Arc::new(self.roots()?), provider.clone())
+                .build()
+                .map_err(|e| e.to_string())?;
+
+        let verifier = Arc::new(TrustVerifier {
+            inner,
+            pins: self.pin_hashes()?,
+            insecure: self.insecure,
+        });
+
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .dangerous()
+            .with_custom_certificate_verifier(verifier);
+
+        self.with_client_auth(builder)
+    }
+
+    fn roots(&self) -> Result<RootCertStore, String> {
+        let mut roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        for path in &self.ca_bundles {
+            for cert in read_certs(path)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+        }
+        Ok(roots)
+    }
+
+    fn pin_hashes(&self) -> Result<Vec<Vec<u8>>, String> {
+        self.pins
+            .iter()
+            .map(|pin| {
+                pin.strip_prefix("sha256/")
+                    .map(|b64| b64.trim_start_matches('/'))
+                    .and_then(|b64| STANDARD.decode(b64).ok())
+                    .filter(|hash| hash.len() == 32)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid pin {}: expected sha256//<base64 SHA-256 of the public key>",
+                            pin
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    fn with_client_auth(
+        &self,
+        builder: ConfigBuilder<ClientConfig, WantsClientCert>,
+    ) -> Result<ClientConfig, String> {
+        let Some((cert, key)) = &self.client_identity else {
+            return Ok(builder.with_no_client_auth());
+        };
+
+        let chain = read_certs(cert)?;
+        let key = PrivateKeyDer::from_pem_file(key)
+            .map_err(|e| format!("{}: no usable private key: {}", key.display(), e))?;
+
+        builder
+            .with_client_auth_cert(chain, key)
+            .map_err(|e| format!("Invalid client certificate {}: {}", cert.display(), e))
+    }
+}
+
+fn read_certs(path: &PathBuf) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    if certs.is_empty() {
+        return Err(format!("{}: no PEM certificates found", path.display()));
+    }
+    Ok(certs)
+}
+
+/// SHA-256 of a certificate's SubjectPublicKeyInfo, as pinned.
+fn spki_hash(cert: &CertificateDer<'_>) -> Option<Vec<u8>> {
+    let (_, cert) = X509Certificate::from_der(cert).ok()?;
+    Some(Sha256::digest(cert.public_key().raw).to_vec())
+}
+
+/// WebPKI verification (unless insecure) followed by the pin check.
+#[derive(Debug)]
+struct TrustVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: Vec<Vec<u8>>,
+    insecure: bool,
+}
+
+impl ServerCertVerifier for TrustVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if !self.insecure {
+            self.inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            )?;
+        }
+
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(spki_hash)
+            .any(|hash| self.pins.contains(&hash));
+        if !self.pins.is_empty() && !pinned {
+            return Err(rustls::Error::General(
+                "no certificate in the chain matches a pinned public key".to_string(),
+            ));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Completes the handshake whatever the certificate, remembering what the
+/// WebPKI verifier thought of it so an untrusted chain can still be reported.
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    verdict: Mutex<Option<rustls::Error>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Err(e) = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            *self.verdict.lock().unwrap() = Some(e);
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+struct Handshake {
+    version: Option<ProtocolVersion>,
+    chain: Vec<CertificateDer<'static>>,
+    verdict: Option<rustls::Error>,
+}
+
+/// Inspects the certificate chain of an `https://` or `wss://` endpoint,
+/// connecting through `proxy` if given. The chain is judged against `trust`,
+/// whatever its `insecure` setting. Returns `None` for any other scheme.
+pub fn inspect(rpc: &str, warn_days: i64, proxy: Option<&Proxy>, trust: &Trust) -> Option<TlsInfo> {
+    let url = Url::parse(rpc).ok()?;
+    if url.scheme() != "https" && url.scheme() != "wss" {
+        return None;
+    }
+
+    // IPv6 hosts come bracketed, which neither sockets nor SANs expect.
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = url.port_or_known_default()?;
+
+    let handshake = match handshake(&host, port, proxy, trust) {
+        Ok(h) => h,
+        Err(e) => {
+            return Some(TlsInfo {
+                version: None,
+                trusted: false,
+                hostname_match: false,
+                days_remaining: None,
+                chain: Vec::new(),
+                warnings: Vec::new(),
+                error: Some(e),
+            });
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+
+    let parsed: Vec<X509Certificate> = handshake
+        .chain
+        .iter()
+        .filter_map(|der| X509Certificate::from_der(der).ok().map(|(_, c)| c))
+        .collect();
+
+    let chain: Vec<Certificate> = parsed.iter().map(|c| describe(c, now)).collect();
+
+    let hostname_match = parsed
+        .first()
+        .is_some_and(|leaf| matches_host(&sans(leaf), &host));
+
+    // webpki checks the chain before the name, so a name error alone means
+    // the chain itself was trusted.
+    let trusted = match &handshake.verdict {
+        None => true,
+        Some(rustls::Error::InvalidCertificate(
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+        )) => true,
+        Some(_) => false,
+    };
+
+    let days_remaining = chain.first().map(|c| c.days_remaining);
+
+    let mut warnings = Vec::new();
+    if let Some(leaf) = chain.first() {
+        if leaf.days_remaining < 0 {
+            warnings.push(format!(
+                "certificate expired {} days ago ({})",
+                -leaf.days_remaining, leaf.not_after
+            ));
+        } else if leaf.days_remaining <= warn_days {
+            warnings.push(format!(
+                "certificate expires in {} days ({})",
+                leaf.days_remaining, leaf.not_after
+            ));
+        }
+    }
+    if !hostname_match {
+        warnings.push(format!("certificate is not valid for {}", host));
+    }
+    if !trusted {
+        if let Some(e) = &handshake.verdict {
+            warnings.push(format!("certificate chain is not trusted: {}", e));
+        }
+    }
+    if let Ok(pins) = trust.pin_hashes() {
+        let pinned = handshake
+            .chain
+            .iter()
+            .filter_map(spki_hash)
+            .any(|hash| pins.contains(&hash));
+        if !pins.is_empty() && !pinned {
+            warnings.push("no certificate matches a pinned public key".to_string());
+        }
+    }
+
+    Some(TlsInfo {
+        version: handshake.version.map(version_name),
+        trusted,
+        hostname_match,
+        days_remaining,
+        chain,
+        warnings,
+        error: None,
+    })
+}
+
+fn handshake(
+    host: &str,
+    port: u16,
+    proxy: Option<&Proxy>,
+    trust: &Trust,
+) -> Result<Handshake, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let inner =
+        WebPkiServerVerifier::builder_with_provider(Arc::new(trust.roots()?), provider.clone())
+            .build()
+            .map_err(|e| e.to_string())?;
+    let verifier = Arc::new(RecordingVerifier {
+        inner,
+        verdict: Mutex::new(None),
+    });
+
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone());
+    let config = trust.with_client_auth(config)?;
+
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+    let mut conn =
+        ClientConnection::new(Arc::new(config), server_name).map_err(|e| e.to_string())?;
+
+    let mut sock = match proxy {
+        Some(proxy) => tunnel(proxy, host, port)?,
+        None => {
+            let addr = (host, port)
+                .to_socket_addrs()
+                .map_err(|e| e.to_string())?
+                .next()
+                .ok_or_else(|| format!("No address for {}", host))?;
+
+            TcpStream::connect_timeout(&addr, HANDSHAKE_TIMEOUT).map_err(|e| e.to_string())?
+        }
+    };
+    sock.set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    sock.set_write_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+
+    while conn.is_handshaking() {
+        conn.complete_io(&mut sock).map_err(|e| e.to_string())?;
+    }
+
+    let chain = conn
+        .peer_certificates()
+        .map(|certs| certs.iter().map(|c| c.clone().into_owned()).collect())
+        .unwrap_or_default();
+
+    let verdict = verifier.verdict.lock().unwrap().take();
+
+    Ok(Handshake {
+        version: conn.protocol_version(),
+        chain,
+        verdict,
+    })
+}
+
+/// Opens a blocking socket to `host:port` through `proxy`.
+fn tunnel(proxy: &Proxy, host: &str, port: u16) -> Result<TcpStream, String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    runtime.block_on(async {
+        let stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, proxy.connect(host, port))
+            .await
+            .map_err(|_| format!("Proxy {}: timed out", proxy))?
+            .map_err(|e| format!("Proxy {}: {}", proxy, e))?;
+
+        let sock = stream.into_std().map_err(|e| e.to_string())?;
+        sock.set_nonblocking(false).map_err(|e| e.to_string())?;
+        Ok(sock)
+    })
+}
+
+fn describe(cert: &X509Certificate, now: i64) -> Certificate {
+    let not_after = cert.validity().not_after;
+
+    Certificate {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        sans: sans(cert),
+        not_after: not_after
+            .to_datetime()
+            .format(&Rfc3339)
+            .unwrap_or_else(|_| not_after.to_string()),
+        days_remaining: (not_after.timestamp() - now).div_euclid(SECONDS_PER_DAY),
+    }
+}
+
+fn sans(cert: &X509Certificate) -> Vec<String> {
+    let Ok(Some(ext)) = cert.subject_alternative_name() else {
+        return Vec::new();
+    };
+
+    ext.value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(dns) => Some(dns.to_string()),
+            GeneralName::IPAddress(bytes) => match bytes.len() {
+                4 => Some(IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?).to_string()),
+                16 => Some(IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?).to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// RFC 6125 matching: exact names, or a wildcard covering exactly one
+/// leftmost label.
+fn matches_host(sans: &[String], host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+
+    sans.iter().any(|san| {
+        let san = san.to_ascii_lowercase();
+
+        match san.strip_prefix("*.") {
+            Some(suffix) => host
+                .split_once('.')
+                .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+            None => san == host,
+        }
+    })
+}
+
+fn version_name(version: ProtocolVersion) -> String {
+    match version {
+        ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+        ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+#[cfg(unix)]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::time::{timeout_at, Instant};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+use crate::auth::Auth;
+use crate::context::{self, Context};
+use crate::jsonrpc::{self, Batch, CallError};
+
+/// How long a call over a persistent connection waits for its response.
+const STREAM_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Carries JSON-RPC traffic to an endpoint, chosen by the URL scheme:
+/// `http(s)://` posts each call, `ws(s)://` and `ipc://` keep one connection
+/// open.
+pub enum Transport {
+    Http {
+        client: reqwest::Client,
+        url: String,
+        auth: Auth,
+        /// Whether the endpoint accepts batches, once one has been tried.
+        batching: Option<bool>,
+    },
+    Stream {
+        conn: Connection,
+        next_id: u64,
+        /// Messages that arrived while waiting for a response, i.e.
+        /// subscription notifications, with when they arrived.
+        pending: VecDeque<(Instant, Value)>,
+    },
+}
+
+/// A persistent connection exchanging one JSON-RPC message at a time.
+pub enum Connection {
+    Ws(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+    /// A geth/reth style IPC socket carrying a stream of JSON values, with
+    /// bytes read past the last complete value.
+    #[cfg(unix)]
+    Ipc {
+        stream: UnixStream,
+        buffer: Vec<u8>,
+    },
+}
+
+impl Transport {
+    /// Connects to `rpc`. HTTP requests go through the shared client and carry
+    /// the credentials in `ctx`; a websocket sends them with the handshake and
+    /// IPC sockets need none.
+    pub async fn connect(rpc: &str, ctx: &Context) -> Result<Transport, CallError> {
+        if rpc.starts_with("ws://") || rpc.starts_with("wss://") {
+            let mut request = rpc
+                .into_client_request()
+                .map_err(|e| CallError::Request(e.to_string()))?;
+            request.headers_mut().extend(ctx.auth.header_map());
+
+            let socket = match ctx.proxy.proxy_for(rpc) {
+                Some(proxy) => {
+                    let url = Url::parse(rpc).map_err(|e| CallError::Request(e.to_string()))?;
+                    let host = url.host_str().unwrap_or_default();
+                    let host = host.trim_start_matches('[').trim_end_matches(']');
+                    let port = url.port_or_known_default().unwrap_or(80);
+
+                    let stream = proxy
+                        .connect(host, port)
+                        .await
+                        .map_err(|e| CallError::Request(format!("Proxy {}: {}", proxy, e)))?;
+                    tokio_tungstenite::client_async_tls_with_config(
+                        request,
+                        stream,
+                        None,
+                        Some(Connector::Rustls(ctx.tls.clone())),
+                    )
+                    .await
+                }
+                None => {
+                    tokio_tungstenite::connect_async_tls_with_config(
+                        request,
+                        None,
+                        false,
+                        Some(Connector::Rustls(ctx.tls.clone())),
+                    )
+                    .await
+                }
+            };
+            let (socket, _) = socket.map_err(|e| CallError::Request(e.to_string()))?;
+            context::count_connection();
+
+            return Ok(Transport::stream(Connection::Ws(Box::new(socket))));
+        }
+
+        // Bare absolute paths are taken to be IPC sockets too.
+        let ipc_path = rpc
+            .strip_prefix("ipc://")
+            .or_else(|| rpc.starts_with('/').then_some(rpc));
+        if let Some(path) = ipc_path {
+            let conn = connect_ipc(path).await?;
+            context::count_connection();
+
+            return Ok(Transport::stream(conn));
+        }
+
+        Ok(Transport::Http {
+            client: ctx.client.clone(),
+            url: rpc.to_string(),
+            auth: ctx.auth.clone(),
+            batching: None,
+        })
+    }
+
+    fn stream(conn: Connection) -> Transport {
+        Transport::Stream {
+            conn,
+            next_id: 1,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Whether the endpoint keeps a connection open and can therefore push
+    /// subscription notifications.
+    pub fn is_streaming(&self) -> bool {
+        matches!(self, Transport::Stream { .. })
+    }
+
+    /// Sends a JSON-RPC 2.0 request and returns its `result` member.
+    pub async fn call(&mut self, method: &str, params: Value) -> Result<Value, CallError> {
+        match self {
+            Transport::Http {
+                client, url, auth, ..
+            } => jsonrpc::call(client, url, auth, method, params).await,
+            Transport::Stream {
+                conn,
+                next_id,
+                pending,
+            } => {
+                let id = *next_id;
+                *next_id += 1;
+
+                context::count_request();
+                conn.send(&jsonrpc::request(method, params, id)).await?;
+
+                let deadline = Instant::now() + STREAM_CALL_TIMEOUT;
+
+                loop {
+                    let msg = conn.read(deadline).await?;
+
+                    if msg["id"].as_u64() == Some(id) {
+                        return jsonrpc::parse_envelope(StatusCode::OK, msg);
+                    }
+
+                    pending.push_back((Instant::now(), msg));
+                }
+            }
+        }
+    }
+
+    /// Makes several independent calls, returning their results in order.
+    /// Over HTTP they go out as one JSON-RPC batch; endpoints that refuse
+    /// batches get the calls one at a time, for the rest of the check too.
+    /// Persistent connections always send them one at a time.
+    pub async fn batch(&mut self, calls: Vec<(&str, Value)>) -> Vec<Result<Value, CallError>> {
+        if let Transport::Http {
+            client,
+            url,
+            auth,
+            batching,
+        } = self
+        {
+            if calls.len() > 1 && *batching != Some(false) {
+                let envelopes: Vec<Value> = calls
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (method, params))| {
+                        jsonrpc::request(method, params.clone(), i as u64 + 1)
+                    })
+                    .collect();
+                let request = client.post(url.as_str()).headers(auth.header_map());
+
+                match jsonrpc::batch(request, &envelopes).await {
+                    Ok(Batch::Answered(results)) => {
+                        *batching = Some(true);
+                        return results;
+                    }
+                    Ok(Batch::Unsupported) => *batching = Some(false),
+                    Err(e) => return calls.iter().map(|_| Err(e.clone())).collect(),
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        for (method, params) in calls {
+            results.push(self.call(method, params).await);
+        }
+        results
+    }
+
+    /// Whether the endpoint accepted a JSON-RPC batch; `None` until one has
+    /// been tried over HTTP.
+    pub fn batching(&self) -> Option<bool> {
+        match self {
+            Transport::Http { batching, .. } => *batching,
+            Transport::Stream { .. } => None,
+        }
+    }
+
+    /// Calls a Tendermint route: a URI request (`/block?height=1`) over HTTP,
+    /// the equivalent JSON-RPC method over a persistent connection.
+    pub async fn route(
+        &mut self,
+        route: &str,
+        params: &[(&str, String)],
+    ) -> Result<Value, CallError> {
+        match self {
+            Transport::Http {
+                client, url, auth, ..
+            } => {
+                let query: Vec<String> =
+                    params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+
+                let target = if query.is_empty() {
+                    format!("{}/{}", url, route)
+                } else {
+                    format!("{}/{}?{}", url, route, query.join("&"))
+                };
+
+                jsonrpc::get(client, &target, auth).await
+            }
+            Transport::Stream { .. } => {
+                let params: serde_json::Map<String, Value> = params
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Value::String(v.clone())))
+                    .collect();
+
+                self.call(route, Value::Object(params)).await
+            }
+        }
+    }
+
+    /// Fetches the raw body of a path on an HTTP endpoint, such as the
+    /// Tendermint route listing at `/`.
+    pub async fn get_text(&mut self, path: &str) -> Result<String, CallError> {
+        match self {
+            Transport::Http {
+                client, url, auth, ..
+            } => {
+                let response = context::send(
+                    client
+                        .get(format!("{}/{}", url, path))
+                        .headers(auth.header_map()),
+                )
+                .await
+                .map_err(|e| CallError::Request(e.to_string()))?;
+
+                response
+                    .text()
+                    .await
+                    .map_err(|e| CallError::Request(e.to_string()))
+            }
+            Transport::Stream { .. } => {
+                Err(CallError::Request("Only available over HTTP".to_string()))
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for the next subscription notification and
+    /// returns it with the time it arrived, which is earlier than now for
+    /// notifications queued during a call.
+    pub async fn next_notification(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<(Instant, Value), CallError> {
+        match self {
+            Transport::Http { .. } => Err(CallError::Request(
+                "Subscriptions require a ws://, wss:// or ipc:// endpoint".to_string(),
+            )),
+            Transport::Stream { conn, pending, .. } => match pending.pop_front() {
+                Some(queued) => Ok(queued),
+                None => {
+                    let msg = conn.read(Instant::now() + timeout).await?;
+                    Ok((Instant::now(), msg))
+                }
+            },
+        }
+    }
+}
+
+impl Connection {
+    async fn send(&mut self, msg: &Value) -> Result<(), CallError> {
+        match self {
+            Connection::Ws(socket) => socket
+                .send(Message::text(msg.to_string()))
+                .await
+                .map_err(|e| CallError::Request(e.to_string())),
+            #[cfg(unix)]
+            Connection::Ipc { stream, .. } => {
+                let mut line = msg.to_string().into_bytes();
+                line.push(b'\n');
+
+                stream
+                    .write_all(&line)
+                    .await
+                    .map_err(|e| CallError::Request(e.to_string()))
+            }
+        }
+    }
+
+    /// Reads the next JSON message, giving up at `deadline`.
+    async fn read(&mut self, deadline: Instant) -> Result<Value, CallError> {
+        match self {
+            Connection::Ws(socket) => loop {
+                let msg = timeout_at(deadline, socket.next())
+                    .await
+                    .map_err(|_| CallError::Timeout)?;
+
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        return serde_json::from_str(text.as_str())
+                            .map_err(|e| CallError::InvalidJson(e.to_string()));
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err(CallError::Request("Websocket closed".to_string()));
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(CallError::Request(e.to_string())),
+                }
+            },
+            #[cfg(unix)]
+            Connection::Ipc { stream, buffer } => loop {
+                // Nodes do not reliably delimit messages, so parse exactly
+                // one JSON value off the front of what has arrived so far.
+                let mut values = serde_json::Deserializer::from_slice(buffer).into_iter::<Value>();
+                match values.next() {
+                    Some(Ok(v)) => {
+                        let consumed = values.byte_offset();
+                        buffer.drain(..consumed);
+                        return Ok(v);
+                    }
+                    Some(Err(e)) if !e.is_eof() => {
+                        return Err(CallError::InvalidJson(e.to_string()));
+                    }
+                    // Only whitespace, or a value that is still arriving.
+                    _ => {}
+                }
+
+                let read = timeout_at(deadline, stream.read_buf(buffer))
+                    .await
+                    .map_err(|_| CallError::Timeout)?;
+
+                match read {
+                    Ok(0) => return Err(CallError::Request("IPC socket closed".to_string())),
+                    Ok(_) => {}
+                    Err(e) => return Err(CallError::Request(e.to_string())),
+                }
+            },
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn connect_ipc(path: &str) -> Result<Connection, CallError> {
+    UnixStream::connect(path)
+        .await
+        .map(|stream| Connection::Ipc {
+            stream,
+            buffer: Vec::new(),
+        })
+        .map_err(|e| CallError::Request(format!("{}: {}", path, e)))
+}
+
+#[cfg(not(unix))]
+async fn connect_ipc(_path: &str) -> Result<Connection, CallError> {
+    Err(CallError::Request(
+        "IPC sockets are only supported on Unix".to_string(),
+    ))
+}
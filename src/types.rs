@@ -1,10 +1,44 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum Protocol {
     Tendermint,
     Ethereum,
     Bitcoin,
+    Engine,
+}
+
+/// A check named independently of its protocol, as `auto` and profiles ask
+/// for it; [`Command::parse`] pairs it with a protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    Status,
+    Health,
+    Block,
+    Archive,
+    Depth,
+    Capabilities,
+    Logs,
+    Audit,
+    Subscription,
+    Subscribe,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -12,6 +46,10 @@ pub enum TendermintMethod {
     Status,
     Health,
     Block { height: Option<u64> },
+    Depth,
+    Audit,
+    Subscription,
+    Subscribe { duration: Duration },
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +57,13 @@ pub enum EthereumMethod {
     Status,
     Health,
     Block { height: Option<u64> },
+    Archive { height: Option<u64> },
+    Depth,
+    Capabilities,
+    Logs,
+    Audit,
+    Subscription,
+    Subscribe { duration: Duration },
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +71,21 @@ pub enum BitcoinMethod {
     Status,
     Health,
     Block { height: Option<u64> },
+    Depth,
+    Audit,
+}
+
+#[derive(Debug, Clone)]
+pub enum EngineMethod {
+    Status,
+}
+
+/// A check whose protocol is detected from the endpoint before it runs.
+#[derive(Debug, Clone)]
+pub struct AutoMethod {
+    pub method: Method,
+    pub height: Option<u64>,
+    pub duration: Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -33,9 +93,82 @@ pub enum Command {
     Tendermint(TendermintMethod),
     Ethereum(EthereumMethod),
     Bitcoin(BitcoinMethod),
+    Engine(EngineMethod),
+    Auto(AutoMethod),
+}
+
+impl Command {
+    /// The check named by `method` for `protocol`, or `None` if the protocol
+    /// has no such method. `height` and `duration` go to the methods taking
+    /// them.
+    pub fn parse(
+        protocol: Protocol,
+        method: Method,
+        height: Option<u64>,
+        duration: Duration,
+    ) -> Option<Command> {
+        match (protocol, method) {
+            (Protocol::Tendermint, Method::Status) => {
+                Some(Command::Tendermint(TendermintMethod::Status))
+            }
+            (Protocol::Tendermint, Method::Health) => {
+                Some(Command::Tendermint(TendermintMethod::Health))
+            }
+            (Protocol::Tendermint, Method::Block) => {
+                Some(Command::Tendermint(TendermintMethod::Block { height }))
+            }
+            (Protocol::Tendermint, Method::Depth) => {
+                Some(Command::Tendermint(TendermintMethod::Depth))
+            }
+            (Protocol::Tendermint, Method::Audit) => {
+                Some(Command::Tendermint(TendermintMethod::Audit))
+            }
+            (Protocol::Tendermint, Method::Subscription) => {
+                Some(Command::Tendermint(TendermintMethod::Subscription))
+            }
+            (Protocol::Tendermint, Method::Subscribe) => {
+                Some(Command::Tendermint(TendermintMethod::Subscribe {
+                    duration,
+                }))
+            }
+
+            (Protocol::Ethereum, Method::Status) => Some(Command::Ethereum(EthereumMethod::Status)),
+            (Protocol::Ethereum, Method::Health) => Some(Command::Ethereum(EthereumMethod::Health)),
+            (Protocol::Ethereum, Method::Block) => {
+                Some(Command::Ethereum(EthereumMethod::Block { height }))
+            }
+            (Protocol::Ethereum, Method::Archive) => {
+                Some(Command::Ethereum(EthereumMethod::Archive { height }))
+            }
+            (Protocol::Ethereum, Method::Depth) => Some(Command::Ethereum(EthereumMethod::Depth)),
+            (Protocol::Ethereum, Method::Capabilities) => {
+                Some(Command::Ethereum(EthereumMethod::Capabilities))
+            }
+            (Protocol::Ethereum, Method::Logs) => Some(Command::Ethereum(EthereumMethod::Logs)),
+            (Protocol::Ethereum, Method::Audit) => Some(Command::Ethereum(EthereumMethod::Audit)),
+            (Protocol::Ethereum, Method::Subscription) => {
+                Some(Command::Ethereum(EthereumMethod::Subscription))
+            }
+            (Protocol::Ethereum, Method::Subscribe) => {
+                Some(Command::Ethereum(EthereumMethod::Subscribe { duration }))
+            }
+
+            (Protocol::Bitcoin, Method::Status) => Some(Command::Bitcoin(BitcoinMethod::Status)),
+            (Protocol::Bitcoin, Method::Health) => Some(Command::Bitcoin(BitcoinMethod::Health)),
+            (Protocol::Bitcoin, Method::Block) => {
+                Some(Command::Bitcoin(BitcoinMethod::Block { height }))
+            }
+            (Protocol::Bitcoin, Method::Depth) => Some(Command::Bitcoin(BitcoinMethod::Depth)),
+            (Protocol::Bitcoin, Method::Audit) => Some(Command::Bitcoin(BitcoinMethod::Audit)),
+
+            (Protocol::Engine, Method::Status) => Some(Command::Engine(EngineMethod::Status)),
+
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ResultData {
     Status {
@@ -48,13 +181,461 @@ pub enum ResultData {
     Block {
         height: Option<u64>,
     },
+    Archive {
+        block: u64,
+        node: Option<NodeKind>,
+    },
+    Depth {
+        latest_block: u64,
+        earliest_block: u64,
+        probes: u32,
+    },
+    Capabilities {
+        methods: Vec<MethodSupport>,
+        /// Whether the endpoint answers JSON-RPC batches; null over
+        /// websockets and IPC, where calls are not batched.
+        batching: Option<bool>,
+    },
+    Logs {
+        latest_block: u64,
+        block_range: LogsLimit,
+        results: LogsLimit,
+    },
+    Audit {
+        findings: Vec<Finding>,
+    },
+    Subscription {
+        event: String,
+        first_event_ms: Option<u64>,
+        height: Option<u64>,
+    },
+    Delivery {
+        event: String,
+        duration_secs: u64,
+        events: u64,
+        first_height: Option<u64>,
+        last_height: Option<u64>,
+        latest_polled: Option<u64>,
+        /// Heights between the first and the last expected one that never
+        /// arrived as events.
+        missed_heights: u64,
+        max_gap_ms: Option<u64>,
+        mean_gap_ms: Option<u64>,
+        reconnections: u32,
+        quality: DeliveryQuality,
+    },
+    Engine {
+        /// Engine methods the execution client reports supporting.
+        capabilities: Vec<String>,
+        syncing: bool,
+        current_block: Option<u64>,
+        highest_block: Option<u64>,
+    },
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryQuality {
+    /// Every expected height arrived without reconnecting.
+    Good,
+    /// Events arrived, but heights were missed or the socket dropped.
+    Degraded,
+    /// No events arrived at all.
+    Failed,
+}
+
+/// How much history an endpoint retains, as seen from a historical state query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    /// Serves state at arbitrary historical blocks.
+    Archive,
+    /// Has the historical block but its state has been pruned.
+    Full,
+    /// No longer has the historical block itself.
+    Pruned,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MethodSupport {
+    pub method: String,
+    pub support: Support,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    Supported,
+    NotFound,
+    Forbidden,
+    RateLimited,
+    /// The endpoint could not be asked at all (connection or parse failure).
+    Error,
+}
+
+/// A discovered `eth_getLogs` limit. `max_accepted` is the largest value the
+/// provider served; when `limited` is false it is only a lower bound.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LogsLimit {
+    pub max_accepted: Option<u64>,
+    pub limited: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Finding {
+    /// Method or route that was found exposed.
+    pub target: String,
+    pub severity: Severity,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CheckResult {
     pub protocol: String,
     pub rpc: String,
     pub reachable: bool,
     pub result: Option<ResultData>,
     pub error: Option<String>,
+    pub tls: Option<TlsInfo>,
+    pub timings: Option<Timings>,
+    /// The check ran with `--insecure`: certificate chains were not verified.
+    pub insecure: bool,
+    /// Labels of the config profile the endpoint came from.
+    pub labels: Option<BTreeMap<String, String>>,
+}
+
+/// Version of the serialized result layout. Bump it whenever a change could
+/// break consumers: a renamed or removed field, a new tag or a changed type.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A `CheckResult` as printed, stamped with the schema version.
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "CheckResult")]
+pub struct Report<'a> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub result: &'a CheckResult,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(result: &'a CheckResult) -> Report<'a> {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            result,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TlsInfo {
+    pub version: Option<String>,
+    /// Whether the chain verifies against the WebPKI roots (hostname aside).
+    pub trusted: bool,
+    pub hostname_match: bool,
+    /// Days until the leaf certificate expires; negative once expired.
+    pub days_remaining: Option<i64>,
+    /// Certificates as sent by the server, leaf first.
+    pub chain: Vec<Certificate>,
+    pub warnings: Vec<String>,
+    /// Set when no handshake could be completed.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    pub not_after: String,
+    pub days_remaining: i64,
+}
+
+/// How long a check took and how it used its connections.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Timings {
+    pub elapsed_ms: u64,
+    /// HTTP requests sent, plus calls made over a websocket or IPC socket.
+    pub requests: u32,
+    /// Connections opened by the check.
+    pub connections: u32,
+    /// Requests served over a connection that was already open.
+    pub reused_connections: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn report(result: Option<ResultData>) -> Value {
+        let result = CheckResult {
+            protocol: "ethereum".to_string(),
+            rpc: "http://localhost:8545".to_string(),
+            reachable: true,
+            result,
+            error: None,
+            tls: None,
+            timings: None,
+            insecure: false,
+            labels: None,
+        };
+
+        serde_json::to_value(Report::new(&result)).unwrap()
+    }
+
+    fn data(result: ResultData) -> Value {
+        report(Some(result))["result"].clone()
+    }
+
+    #[test]
+    fn report_envelope() {
+        assert_eq!(
+            report(None),
+            json!({
+                "schema_version": 1,
+                "protocol": "ethereum",
+                "rpc": "http://localhost:8545",
+                "reachable": true,
+                "result": null,
+                "error": null,
+                "tls": null,
+                "timings": null,
+                "insecure": false,
+                "labels": null,
+            })
+        );
+    }
+
+    #[test]
+    fn basic_results() {
+        assert_eq!(
+            data(ResultData::Status {
+                latest_block: Some(100),
+                syncing: Some(false),
+            }),
+            json!({"type": "status", "latest_block": 100, "syncing": false})
+        );
+        assert_eq!(
+            data(ResultData::Health { healthy: true }),
+            json!({"type": "health", "healthy": true})
+        );
+        assert_eq!(
+            data(ResultData::Block { height: None }),
+            json!({"type": "block", "height": null})
+        );
+    }
+
+    #[test]
+    fn history_results() {
+        assert_eq!(
+            data(ResultData::Archive {
+                block: 1,
+                node: Some(NodeKind::Full),
+            }),
+            json!({"type": "archive", "block": 1, "node": "full"})
+        );
+        assert_eq!(
+            data(ResultData::Depth {
+                latest_block: 100,
+                earliest_block: 40,
+                probes: 7,
+            }),
+            json!({"type": "depth", "latest_block": 100, "earliest_block": 40, "probes": 7})
+        );
+    }
+
+    #[test]
+    fn capability_results() {
+        assert_eq!(
+            data(ResultData::Capabilities {
+                methods: vec![MethodSupport {
+                    method: "debug_traceTransaction".to_string(),
+                    support: Support::RateLimited,
+                    message: None,
+                }],
+                batching: Some(true),
+            }),
+            json!({
+                "type": "capabilities",
+                "methods": [
+                    {"method": "debug_traceTransaction", "support": "rate_limited", "message": null}
+                ],
+                "batching": true
+            })
+        );
+        assert_eq!(
+            data(ResultData::Logs {
+                latest_block: 100,
+                block_range: LogsLimit {
+                    max_accepted: Some(10),
+                    limited: true,
+                    error: Some("range too large".to_string()),
+                },
+                results: LogsLimit {
+                    max_accepted: Some(3),
+                    limited: false,
+                    error: None,
+                },
+            }),
+            json!({
+                "type": "logs",
+                "latest_block": 100,
+                "block_range": {"max_accepted": 10, "limited": true, "error": "range too large"},
+                "results": {"max_accepted": 3, "limited": false, "error": null}
+            })
+        );
+        assert_eq!(
+            data(ResultData::Audit {
+                findings: vec![Finding {
+                    target: "admin_peers".to_string(),
+                    severity: Severity::Critical,
+                    description: "exposed".to_string(),
+                }],
+            }),
+            json!({
+                "type": "audit",
+                "findings": [
+                    {"target": "admin_peers", "severity": "critical", "description": "exposed"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn streaming_results() {
+        assert_eq!(
+            data(ResultData::Subscription {
+                event: "newHeads".to_string(),
+                first_event_ms: Some(500),
+                height: Some(101),
+            }),
+            json!({"type": "subscription", "event": "newHeads", "first_event_ms": 500, "height": 101})
+        );
+        assert_eq!(
+            data(ResultData::Delivery {
+                event: "newHeads".to_string(),
+                duration_secs: 60,
+                events: 5,
+                first_height: Some(101),
+                last_height: Some(105),
+                latest_polled: Some(105),
+                missed_heights: 0,
+                max_gap_ms: Some(12_000),
+                mean_gap_ms: Some(11_900),
+                reconnections: 0,
+                quality: DeliveryQuality::Good,
+            }),
+            json!({
+                "type": "delivery",
+                "event": "newHeads",
+                "duration_secs": 60,
+                "events": 5,
+                "first_height": 101,
+                "last_height": 105,
+                "latest_polled": 105,
+                "missed_heights": 0,
+                "max_gap_ms": 12000,
+                "mean_gap_ms": 11900,
+                "reconnections": 0,
+                "quality": "good"
+            })
+        );
+        assert_eq!(
+            data(ResultData::Engine {
+                capabilities: vec!["engine_newPayloadV4".to_string()],
+                syncing: false,
+                current_block: None,
+                highest_block: None,
+            }),
+            json!({
+                "type": "engine",
+                "capabilities": ["engine_newPayloadV4"],
+                "syncing": false,
+                "current_block": null,
+                "highest_block": null
+            })
+        );
+    }
+
+    #[test]
+    fn tls_info() {
+        let tls = TlsInfo {
+            version: Some("TLSv1.3".to_string()),
+            trusted: true,
+            hostname_match: true,
+            days_remaining: Some(30),
+            chain: vec![Certificate {
+                subject: "CN=example.com".to_string(),
+                issuer: "CN=R3".to_string(),
+                sans: vec!["example.com".to_string()],
+                not_after: "2026-01-01T00:00:00Z".to_string(),
+                days_remaining: 30,
+            }],
+            warnings: Vec::new(),
+            error: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(tls).unwrap(),
+            json!({
+                "version": "TLSv1.3",
+                "trusted": true,
+                "hostname_match": true,
+                "days_remaining": 30,
+                "chain": [{
+                    "subject": "CN=example.com",
+                    "issuer": "CN=R3",
+                    "sans": ["example.com"],
+                    "not_after": "2026-01-01T00:00:00Z",
+                    "days_remaining": 30
+                }],
+                "warnings": [],
+                "error": null
+            })
+        );
+    }
+
+    #[test]
+    fn timings() {
+        let timings = Timings {
+            elapsed_ms: 120,
+            requests: 3,
+            connections: 1,
+            reused_connections: 2,
+        };
+
+        assert_eq!(
+            serde_json::to_value(timings).unwrap(),
+            json!({
+                "elapsed_ms": 120,
+                "requests": 3,
+                "connections": 1,
+                "reused_connections": 2
+            })
+        );
+    }
+
+    /// The published schema must match the types; regenerate it with
+    /// `cargo run -- schema > schema/check-result.schema.json` and bump
+    /// `SCHEMA_VERSION` if the change is not backwards compatible.
+    #[test]
+    fn published_schema_is_current() {
+        let published: Value =
+            serde_json::from_str(include_str!("../schema/check-result.schema.json")).unwrap();
+        let generated = serde_json::to_value(schemars::schema_for!(Report)).unwrap();
+
+        assert_eq!(published, generated);
+    }
 }
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::PathBuf;
+
+use rpc_checker::config::Config;
+use rpc_checker::types::{Method, Protocol};
+
+fn write(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rpc-checker-{}-{}", std::process::id(), name));
+    fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn toml_profiles() {
+    std::env::set_var("RPC_CHECKER_TEST_KEY", "s3cret");
+    let path = write(
+        "profiles.toml",
+        r#"
+[profiles.mainnet-eth]
+protocol = "ethereum"
+url = "https://eth.example.com/v3/${RPC_CHECKER_TEST_KEY}"
+method = "status"
+headers = { "x-api-key" = "${RPC_CHECKER_TEST_KEY}" }
+labels = { network = "mainnet", region = "${RPC_CHECKER_TEST_REGION:-eu}" }
+
+[profiles.mainnet-eth.thresholds]
+tls_warn_days = 30
+
+[profiles.unused]
+protocol = "bitcoin"
+url = "http://${RPC_CHECKER_TEST_UNSET}@127.0.0.1:8332"
+"#,
+    );
+
+    let config = Config::load(&path).unwrap();
+    let profile = config.profile("mainnet-eth").unwrap();
+
+    assert_eq!(profile.protocol, Protocol::Ethereum);
+    assert_eq!(profile.url, "https://eth.example.com/v3/s3cret");
+    assert_eq!(profile.method, Some(Method::Status));
+    assert_eq!(profile.headers["x-api-key"], "s3cret");
+    assert_eq!(profile.labels["region"], "eu");
+    assert_eq!(profile.thresholds.tls_warn_days, Some(30));
+
+    // Variables only need to be set for the profiles in use.
+    let err = config.profile("unused").unwrap_err();
+    assert!(err.contains("line 14"), "{}", err);
+    assert!(err.contains("RPC_CHECKER_TEST_UNSET is not set"), "{}", err);
+
+    assert!(config.profile("missing").is_err());
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn yaml_profiles() {
+    let path = write(
+        "profiles.yaml",
+        "profiles:\n  cosmos:\n    protocol: tendermint\n    url: http://127.0.0.1:26657\n",
+    );
+
+    let config = Config::load(&path).unwrap();
+    let profile = config.profile("cosmos").unwrap();
+    assert_eq!(profile.protocol, Protocol::Tendermint);
+    assert_eq!(profile.method, None);
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+fn variable_errors_point_at_the_profile() {
+    let cases = [
+        (
+            "shared.toml",
+            "[profiles.a]\nprotocol = \"ethereum\"\nurl = \"http://${RPC_CHECKER_TEST_SHARED}\"\n\n[profiles.b]\nprotocol = \"bitcoin\"\nurl = \"http://x\"\nheaders = { \"x-api-key\" = \"${RPC_CHECKER_TEST_SHARED}\" }\n",
+            "line 8:",
+        ),
+        (
+            "shared.yaml",
+            "profiles:\n  a:\n    protocol: ethereum\n    url: http://${RPC_CHECKER_TEST_SHARED}\n  b:\n    protocol: bitcoin\n    url: http://x\n    headers:\n      x-api-key: ${RPC_CHECKER_TEST_SHARED}\n",
+            "line 9:",
+        ),
+    ];
+
+    for (name, text, expected) in cases {
+        let path = write(name, text);
+        let err = Config::load(&path).unwrap().profile("b").unwrap_err();
+        assert!(err.contains(expected), "{}: {}", name, err);
+        assert!(err.contains("profile b:"), "{}: {}", name, err);
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[test]
+fn errors_point_at_the_line() {
+    let cases = [
+        (
+            "protocol.toml",
+            "[profiles.a]\nprotocol = \"etherium\"\nurl = \"http://x\"\n",
+            "line 2",
+        ),
+        (
+            "field.toml",
+            "[profiles.a]\nprotocol = \"ethereum\"\nurl = \"http://x\"\nmthod = \"status\"\n",
+            "line 4",
+        ),
+        (
+            "method.yaml",
+            "profiles:\n  a:\n    protocol: bitcoin\n    url: http://x\n    method: logs\n",
+            "line 5: profile a: unsupported method logs",
+        ),
+        (
+            "method.toml",
+            "[profiles.a]\nprotocol = \"ethereum\"\nurl = \"http://x\"\n\n[profiles.b]\nprotocol = \"bitcoin\"\nurl = \"http://x\"\nmethod = \"logs\"\n",
+            "line 8: profile b: unsupported method logs",
+        ),
+        (
+            "field.yaml",
+            "profiles:\n  a:\n    protocol: bitcoin\n    url: [http://x]\n",
+            "line 4",
+        ),
+    ];
+
+    for (name, text, expected) in cases {
+        let path = write(name, text);
+        let err = Config::load(&path).unwrap_err();
+        assert!(err.contains(expected), "{}: {}", name, err);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,601 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rpc_checker::context::Context;
+use rpc_checker::mock::{MockServer, Scenario};
+use rpc_checker::rpc::blocking::check;
+use rpc_checker::types::{
+    AutoMethod, BitcoinMethod, CheckResult, Command, DeliveryQuality, EngineMethod, EthereumMethod,
+    Method, NodeKind, Protocol, ResultData, Support, TendermintMethod,
+};
+
+fn scenario(name: &str) -> Scenario {
+    Scenario::load(name).unwrap()
+}
+
+fn serve(protocol: Protocol, scenario: Scenario) -> MockServer {
+    MockServer::start(protocol, scenario, "127.0.0.1:0").unwrap()
+}
+
+fn run(server: &MockServer, cmd: Command) -> CheckResult {
+    check(cmd, &server.url(), &Context::default())
+}
+
+fn status(result: &CheckResult) -> (Option<u64>, Option<bool>) {
+    match &result.result {
+        Some(ResultData::Status {
+            latest_block,
+            syncing,
+        }) => (*latest_block, *syncing),
+        other => panic!("expected a status result, got {:?}", other),
+    }
+}
+
+#[test]
+fn ethereum_healthy() {
+    let server = serve(Protocol::Ethereum, scenario("healthy"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Status));
+    assert!(result.reachable);
+    let (latest, syncing) = status(&result);
+    assert!(latest.unwrap() >= 1000);
+    assert_eq!(syncing, Some(false));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Health));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Health { healthy: true })
+    ));
+
+    let result = run(
+        &server,
+        Command::Ethereum(EthereumMethod::Block { height: Some(42) }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Block { height: Some(42) })
+    ));
+}
+
+#[test]
+fn ethereum_reuses_connections() {
+    let server = serve(Protocol::Ethereum, scenario("healthy"));
+
+    // Both status calls go out as one batch.
+    let result = run(&server, Command::Ethereum(EthereumMethod::Status));
+    let timings = result.timings.unwrap();
+    assert_eq!(timings.requests, 1);
+    assert_eq!(timings.connections, 1);
+    assert_eq!(timings.reused_connections, 0);
+}
+
+#[test]
+fn ethereum_archive_reuses_connections() {
+    let server = serve(Protocol::Ethereum, scenario("healthy"));
+
+    let result = run(
+        &server,
+        Command::Ethereum(EthereumMethod::Archive { height: None }),
+    );
+    let timings = result.timings.unwrap();
+    assert_eq!(timings.requests, 2);
+    assert_eq!(timings.connections, 1);
+    assert_eq!(timings.reused_connections, 1);
+}
+
+#[test]
+fn ethereum_syncing() {
+    let server = serve(Protocol::Ethereum, scenario("syncing"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Status));
+    assert!(result.reachable);
+    assert_eq!(status(&result).1, Some(true));
+}
+
+#[test]
+fn ethereum_stalled() {
+    let server = serve(Protocol::Ethereum, scenario("stalled"));
+
+    let first = status(&run(&server, Command::Ethereum(EthereumMethod::Status))).0;
+    std::thread::sleep(Duration::from_millis(100));
+    let second = status(&run(&server, Command::Ethereum(EthereumMethod::Status))).0;
+
+    assert_eq!(first, Some(1000));
+    assert_eq!(first, second);
+}
+
+#[test]
+fn ethereum_erroring() {
+    let server = serve(Protocol::Ethereum, scenario("erroring"));
+
+    // The node answered, only with errors.
+    let result = run(&server, Command::Ethereum(EthereumMethod::Status));
+    assert!(result.reachable);
+    assert_eq!(status(&result), (None, Some(false)));
+    assert_eq!(
+        result.error.as_deref(),
+        Some("RPC error -32603: internal error")
+    );
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Health));
+    assert!(result.reachable);
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Health { healthy: false })
+    ));
+}
+
+#[test]
+fn ethereum_malformed() {
+    let server = serve(Protocol::Ethereum, scenario("malformed"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Status));
+    assert!(!result.reachable);
+    assert!(result.error.unwrap().starts_with("Invalid JSON"));
+}
+
+#[test]
+fn ethereum_slow() {
+    let mut slow = scenario("slow");
+    slow.delay_ms = 200;
+    let server = serve(Protocol::Ethereum, slow);
+
+    let started = Instant::now();
+    let result = run(&server, Command::Ethereum(EthereumMethod::Health));
+
+    assert!(result.reachable);
+    assert!(started.elapsed() >= Duration::from_millis(200));
+}
+
+#[test]
+fn ethereum_archive() {
+    let archive = serve(Protocol::Ethereum, scenario("stalled"));
+    let result = run(
+        &archive,
+        Command::Ethereum(EthereumMethod::Archive { height: None }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Archive {
+            block: 1,
+            node: Some(NodeKind::Archive)
+        })
+    ));
+
+    let mut full = scenario("stalled");
+    full.earliest_state = Some(900);
+    let full = serve(Protocol::Ethereum, full);
+    let result = run(
+        &full,
+        Command::Ethereum(EthereumMethod::Archive { height: None }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Archive {
+            node: Some(NodeKind::Full),
+            ..
+        })
+    ));
+
+    let mut pruned = scenario("stalled");
+    pruned.earliest_block = 500;
+    let pruned = serve(Protocol::Ethereum, pruned);
+    let result = run(
+        &pruned,
+        Command::Ethereum(EthereumMethod::Archive { height: None }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Archive {
+            node: Some(NodeKind::Pruned),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn ethereum_depth() {
+    let mut pruned = scenario("stalled");
+    pruned.earliest_block = 500;
+    let server = serve(Protocol::Ethereum, pruned);
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Depth));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Depth {
+            latest_block: 1000,
+            earliest_block: 500,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn ethereum_depth_archive() {
+    let server = serve(Protocol::Ethereum, scenario("stalled"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Depth));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Depth {
+            latest_block: 1000,
+            earliest_block: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn ethereum_capabilities() {
+    let server = serve(Protocol::Ethereum, scenario("healthy"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Capabilities));
+    let Some(ResultData::Capabilities { methods, batching }) = &result.result else {
+        panic!("expected a capabilities result");
+    };
+
+    let support = |name: &str| {
+        methods
+            .iter()
+            .find(|m| m.method == name)
+            .map(|m| m.support)
+            .unwrap()
+    };
+    assert_eq!(support("eth_getBlockByNumber"), Support::Supported);
+    assert_eq!(support("debug_traceBlockByNumber"), Support::NotFound);
+    assert_eq!(*batching, Some(true));
+    assert_eq!(result.timings.unwrap().requests, 1);
+}
+
+#[test]
+fn ethereum_without_batches() {
+    let mut unbatched = scenario("stalled");
+    unbatched.reject_batches = true;
+    let server = serve(Protocol::Ethereum, unbatched);
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Status));
+    assert_eq!(status(&result), (Some(1000), Some(false)));
+    // The refused batch, then each call on its own.
+    assert_eq!(result.timings.unwrap().requests, 3);
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Capabilities));
+    let Some(ResultData::Capabilities { methods, batching }) = result.result else {
+        panic!("expected a capabilities result");
+    };
+    assert_eq!(batching, Some(false));
+    assert!(methods.iter().all(|m| m.support != Support::Error));
+}
+
+#[test]
+fn ethereum_logs() {
+    let mut limited = scenario("stalled");
+    limited.logs_range_limit = Some(2_000);
+    let server = serve(Protocol::Ethereum, limited);
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Logs));
+    let Some(ResultData::Logs { block_range, .. }) = result.result else {
+        panic!("expected a logs result");
+    };
+    assert_eq!(block_range.max_accepted, Some(1_001));
+    assert!(!block_range.limited);
+}
+
+#[test]
+fn ethereum_logs_limited() {
+    let mut limited = scenario("stalled");
+    limited.height = 50_000;
+    limited.logs_range_limit = Some(2_000);
+    let server = serve(Protocol::Ethereum, limited);
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Logs));
+    let Some(ResultData::Logs { block_range, .. }) = result.result else {
+        panic!("expected a logs result");
+    };
+    assert_eq!(block_range.max_accepted, Some(2_000));
+    assert!(block_range.limited);
+}
+
+#[test]
+fn ethereum_audit() {
+    let server = serve(Protocol::Ethereum, scenario("healthy"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Audit));
+    assert!(result.reachable);
+    assert!(matches!(result.result, Some(ResultData::Audit { findings }) if findings.is_empty()));
+}
+
+#[test]
+fn ethereum_subscription_needs_a_socket() {
+    let server = serve(Protocol::Ethereum, scenario("healthy"));
+
+    let result = run(&server, Command::Ethereum(EthereumMethod::Subscription));
+    assert!(!result.reachable);
+    assert!(result.error.unwrap().starts_with("Subscriptions require"));
+}
+
+/// A scenario producing a block every 100ms, to keep subscriptions short.
+fn fast_blocks() -> Scenario {
+    let mut fast = scenario("healthy");
+    fast.block_time_ms = Some(100);
+    fast
+}
+
+fn run_ws(server: &MockServer, cmd: Command) -> CheckResult {
+    check(cmd, &server.ws_url().unwrap(), &Context::default())
+}
+
+#[test]
+fn ethereum_subscription() {
+    let server = serve(Protocol::Ethereum, fast_blocks());
+
+    let result = run_ws(&server, Command::Ethereum(EthereumMethod::Subscription));
+    assert!(result.reachable, "{:?}", result.error);
+    let Some(ResultData::Subscription {
+        event,
+        first_event_ms,
+        height,
+    }) = result.result
+    else {
+        panic!("expected a subscription result");
+    };
+    assert_eq!(event, "newHeads");
+    assert!(first_event_ms.unwrap() < 1000);
+    assert!(height.unwrap() > 1000);
+}
+
+#[test]
+fn ethereum_subscribe() {
+    let server = serve(Protocol::Ethereum, fast_blocks());
+
+    let result = run_ws(
+        &server,
+        Command::Ethereum(EthereumMethod::Subscribe {
+            duration: Duration::from_secs(2),
+        }),
+    );
+    let Some(ResultData::Delivery {
+        events,
+        missed_heights,
+        reconnections,
+        latest_polled,
+        quality,
+        ..
+    }) = result.result
+    else {
+        panic!("expected a delivery result, got {:?}", result.error);
+    };
+    assert!(events >= 10, "{} events", events);
+    assert_eq!(missed_heights, 0);
+    assert_eq!(reconnections, 0);
+    assert!(latest_polled.is_some());
+    assert_eq!(quality, DeliveryQuality::Good);
+}
+
+#[test]
+fn engine_status() {
+    let server = serve(Protocol::Engine, scenario("syncing"));
+
+    let result = run(&server, Command::Engine(EngineMethod::Status));
+    let Some(ResultData::Engine {
+        capabilities,
+        syncing,
+        current_block,
+        highest_block,
+    }) = result.result
+    else {
+        panic!("expected an engine result");
+    };
+
+    assert!(capabilities.contains(&"engine_newPayloadV4".to_string()));
+    assert!(syncing);
+    assert!(current_block.unwrap() < highest_block.unwrap());
+}
+
+#[test]
+fn tendermint_healthy() {
+    let server = serve(Protocol::Tendermint, scenario("stalled"));
+
+    let result = run(&server, Command::Tendermint(TendermintMethod::Status));
+    assert_eq!(status(&result), (Some(1000), Some(false)));
+
+    let result = run(&server, Command::Tendermint(TendermintMethod::Health));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Health { healthy: true })
+    ));
+
+    let result = run(
+        &server,
+        Command::Tendermint(TendermintMethod::Block { height: Some(7) }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Block { height: Some(7) })
+    ));
+
+    let result = run(&server, Command::Tendermint(TendermintMethod::Audit));
+    assert!(matches!(result.result, Some(ResultData::Audit { findings }) if findings.is_empty()));
+}
+
+#[test]
+fn tendermint_syncing() {
+    let server = serve(Protocol::Tendermint, scenario("syncing"));
+
+    let result = run(&server, Command::Tendermint(TendermintMethod::Status));
+    assert_eq!(status(&result).1, Some(true));
+}
+
+#[test]
+fn tendermint_depth() {
+    let mut pruned = scenario("stalled");
+    pruned.earliest_block = 377;
+    let server = serve(Protocol::Tendermint, pruned);
+
+    let result = run(&server, Command::Tendermint(TendermintMethod::Depth));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Depth {
+            earliest_block: 377,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn tendermint_health_empty_body() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.read(&mut [0u8; 1024]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        }
+    });
+
+    let result = check(
+        Command::Tendermint(TendermintMethod::Health),
+        &url,
+        &Context::default(),
+    );
+    assert!(result.reachable);
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Health { healthy: true })
+    ));
+}
+
+#[test]
+fn tendermint_subscribe() {
+    let server = serve(Protocol::Tendermint, fast_blocks());
+
+    let result = run_ws(&server, Command::Tendermint(TendermintMethod::Subscription));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Subscription { height: Some(h), .. }) if h > 1000
+    ));
+
+    let result = run_ws(
+        &server,
+        Command::Tendermint(TendermintMethod::Subscribe {
+            duration: Duration::from_secs(1),
+        }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Delivery {
+            missed_heights: 0,
+            latest_polled: Some(_),
+            quality: DeliveryQuality::Good,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn tendermint_erroring() {
+    let server = serve(Protocol::Tendermint, scenario("erroring"));
+
+    let result = run(&server, Command::Tendermint(TendermintMethod::Status));
+    assert!(!result.reachable);
+    assert!(result.error.is_some());
+}
+
+#[test]
+fn bitcoin_healthy() {
+    let server = serve(Protocol::Bitcoin, scenario("stalled"));
+
+    let result = run(&server, Command::Bitcoin(BitcoinMethod::Status));
+    assert_eq!(status(&result), (Some(1000), Some(false)));
+
+    let result = run(&server, Command::Bitcoin(BitcoinMethod::Health));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Health { healthy: true })
+    ));
+
+    let result = run(
+        &server,
+        Command::Bitcoin(BitcoinMethod::Block { height: Some(10) }),
+    );
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Block { height: Some(10) })
+    ));
+
+    let result = run(&server, Command::Bitcoin(BitcoinMethod::Audit));
+    assert!(matches!(result.result, Some(ResultData::Audit { findings }) if findings.is_empty()));
+}
+
+#[test]
+fn bitcoin_syncing() {
+    let server = serve(Protocol::Bitcoin, scenario("syncing"));
+
+    let result = run(&server, Command::Bitcoin(BitcoinMethod::Status));
+    assert_eq!(status(&result).1, Some(true));
+}
+
+#[test]
+fn bitcoin_depth() {
+    let mut pruned = scenario("stalled");
+    pruned.earliest_block = 600;
+    let server = serve(Protocol::Bitcoin, pruned);
+
+    let result = run(&server, Command::Bitcoin(BitcoinMethod::Depth));
+    assert!(matches!(
+        result.result,
+        Some(ResultData::Depth {
+            latest_block: 1000,
+            earliest_block: 600,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn bitcoin_malformed() {
+    let server = serve(Protocol::Bitcoin, scenario("malformed"));
+
+    let result = run(&server, Command::Bitcoin(BitcoinMethod::Status));
+    assert!(!result.reachable);
+}
+
+fn auto(method: Method) -> Command {
+    Command::Auto(AutoMethod {
+        method,
+        height: None,
+        duration: Duration::from_secs(60),
+    })
+}
+
+#[test]
+fn auto_detects_the_protocol() {
+    for (protocol, name) in [
+        (Protocol::Tendermint, "tendermint"),
+        (Protocol::Ethereum, "ethereum"),
+        (Protocol::Bitcoin, "bitcoin"),
+    ] {
+        let server = serve(protocol, scenario("stalled"));
+
+        let result = run(&server, auto(Method::Status));
+        assert_eq!(result.protocol, name);
+        assert_eq!(status(&result).0, Some(1000));
+    }
+}
+
+#[test]
+fn auto_reports_missing_checks() {
+    let server = serve(Protocol::Bitcoin, scenario("healthy"));
+
+    let result = run(&server, auto(Method::Archive));
+    assert_eq!(result.protocol, "bitcoin");
+    assert!(result.reachable);
+    assert!(result.error.unwrap().contains("no archive check"));
+
+    let server = serve(Protocol::Ethereum, scenario("malformed"));
+
+    let result = run(&server, auto(Method::Status));
+    assert_eq!(result.protocol, "unknown");
+    assert!(result.reachable);
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::PathBuf;
+
+use rpc_checker::auth::Auth;
+use rpc_checker::context::{ClientConfig, Context};
+use rpc_checker::mock::{MockServer, Scenario};
+use rpc_checker::record::{self, Tape};
+use rpc_checker::redact::Redactor;
+use rpc_checker::rpc::blocking::check;
+use rpc_checker::types::{Command, EthereumMethod, Protocol, ResultData};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rpc-checker-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn record_then_replay() {
+    let dir = temp_dir("record");
+    let redactor = Redactor::new(&[]).unwrap();
+    let auth = Auth::new(&["x-api-key: hunter2".to_string()], None).unwrap();
+    let ctx = Context::new(&ClientConfig::default(), auth).unwrap();
+    let cmd = Command::Ethereum(EthereumMethod::Depth);
+
+    let server = MockServer::start(
+        Protocol::Ethereum,
+        Scenario::load("stalled").unwrap(),
+        "127.0.0.1:0",
+    )
+    .unwrap();
+    let rpc = server.url();
+    let session = record::session_dir(&dir, &rpc, &redactor);
+
+    let tape = Tape::record(&rpc, &session, &ctx, &redactor).unwrap();
+    let recorded = check(cmd.clone(), &tape.url(), &ctx);
+    tape.finish().unwrap();
+    drop(server);
+
+    assert!(recorded.reachable);
+    let first = fs::read_to_string(session.join("0001.json")).unwrap();
+    assert!(first.contains(r#""x-api-key": "***""#));
+    assert!(!first.contains("hunter2"));
+
+    // The endpoint is gone; the recording answers in its place.
+    let tape = Tape::replay(&session).unwrap();
+    let replayed = check(cmd, &tape.url(), &Context::default());
+    tape.finish().unwrap();
+
+    assert!(replayed.reachable);
+    assert!(matches!(
+        replayed.result,
+        Some(ResultData::Depth {
+            latest_block: 1000,
+            earliest_block: 0,
+            ..
+        })
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn record_redacts_request_errors() {
+    let key = "0123456789abcdef0123456789abcdef";
+    let dir = temp_dir("record-error");
+    let redactor = Redactor::new(&[]).unwrap();
+    let ctx = Context::default();
+    let rpc = format!("http://127.0.0.1:1/{}", key);
+
+    let tape = Tape::record(&rpc, &dir, &ctx, &redactor).unwrap();
+    let result = check(Command::Ethereum(EthereumMethod::Health), &tape.url(), &ctx);
+    tape.finish().unwrap();
+
+    assert!(!result.reachable);
+    let first = fs::read_to_string(dir.join("0001.json")).unwrap();
+    assert!(first.contains("127.0.0.1:1/***"), "{}", first);
+    assert!(!first.contains(key), "{}", first);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn session_dirs_keep_keys_apart() {
+    let dir = PathBuf::from("fixtures");
+    let redactor = Redactor::new(&[]).unwrap();
+    let a = record::session_dir(
+        &dir,
+        "https://eth.example.com/v3/0123456789abcdef0123456789abcdef",
+        &redactor,
+    );
+    let b = record::session_dir(
+        &dir,
+        "https://eth.example.com/v3/fedcba9876543210fedcba9876543210",
+        &redactor,
+    );
+
+    assert_ne!(a, b);
+    for session in [&a, &b] {
+        let name = session.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("eth.example.com_v3-"), "{}", name);
+        assert!(!name.contains("0123456789abcdef"), "{}", name);
+    }
+}
+
+#[test]
+fn record_replaces_old_exchanges() {
+    let dir = temp_dir("record-again");
+    let redactor = Redactor::new(&[]).unwrap();
+    let ctx = Context::default();
+    let rpc = "http://127.0.0.1:1/";
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("0009.json"), "stale").unwrap();
+
+    let tape = Tape::record(rpc, &dir, &ctx, &redactor).unwrap();
+    check(Command::Ethereum(EthereumMethod::Health), &tape.url(), &ctx);
+    tape.finish().unwrap();
+
+    assert!(dir.join("0001.json").exists());
+    assert!(!dir.join("0009.json").exists());
+    assert!(dir.join("session.json").exists());
+    Tape::replay(&dir).unwrap().finish().unwrap();
+
+    let _ = fs::remove_dir_all(&dir);
+}